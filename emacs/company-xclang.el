@@ -35,13 +35,17 @@
        (basic-save-buffer))
   (let ((process-res 0)
         location file search))
+  ;; Columns are counted in characters, `current-column' would expand tabs
   (setq location (format "%d:%d"
                          (line-number-at-pos)
-                         (current-column)))
+                         (- (point) (line-beginning-position))))
   (setq file buffer-file-name)
   (setq search (substring-no-properties prefix))
   (with-temp-buffer
-    (setq process-res (call-process company-xclang-executable nil t nil "code-completion" "-p" search "-l" location file))
+    ;; Candidates are yasnippet snippets regardless of snippet_format in the configuration
+    (setq process-res (call-process company-xclang-executable nil t nil "code-completion"
+                                    "-f" "yas" "--column-encoding" "char"
+                                    "-p" search "-l" location file))
     (company-xclang--parse-output)))
 
 (defun company-xclang--annotation (candidate)
@@ -64,6 +68,10 @@
     (candidates (company-xclang--candidates arg))
     (annotation (company-xclang--annotation arg))
     (post-completion (company-xclang--expand arg))
+    ;; Prefix is fuzzy matched and ranked by xclang, company shouldn't
+    ;; re-filter cached candidates or re-sort them
+    (no-cache t)
+    (sorted t)
     ))
 
 (push 'company-xclang company-backends)
@@ -11,32 +11,49 @@
   (executable-find "/Users/arthurevstifeev/github/xclang_tool/bin/xclang")
   "Location of xclang executable.")
 
+(defun xclang--jump-to (location)
+  (let ((line_split (split-string location ":")))
+    (find-file (car line_split))
+    (goto-line (string-to-number (cadr line_split)))
+    (forward-char (- (string-to-number (car (last line_split))) 1))))
+
 (defun xclang--parse-output ()
-  (let (line_split file line column)
-    (setq line_split (split-string (buffer-string) ":"))
-    (setq file (car line_split))
-    (setq line (string-to-number (cadr line_split)))
-    (setq column (string-to-number (car (last line_split))))
-    (if (string= "" file)
-        (message "Unable to find definition")
-      (find-file file)
-      (goto-line line)
-      (move-to-column (- column 1)))
+  ;; Several candidates are offered for selection, ex: implementations in subclasses
+  (let ((locations (split-string (buffer-string) "\n" t)))
+    (cond ((null locations)
+           (message "Unable to find definition"))
+          ((null (cdr locations))
+           (xclang--jump-to (car locations)))
+          (t
+           (xclang--jump-to (completing-read "Location: " locations nil t))))
     ))
 
-(defun xclang-definition ()
-  (interactive)
+(defun xclang--goto (command)
   (and (buffer-modified-p)
        (basic-save-buffer))
   (let ((process-res 0)
         location file))
+  ;; Columns are counted in characters, `current-column' would expand tabs
   (setq location (format "%d:%d"
                          (line-number-at-pos)
-                         (current-column)))
+                         (- (point) (line-beginning-position))))
   (setq file buffer-file-name)
   (with-temp-buffer
-    (setq process-res (call-process xclang-definition-executable nil t nil "goto-definition" "-l" location file))
+    (setq process-res (call-process xclang-definition-executable nil t nil command
+                                    "--column-encoding" "char" "-l" location file))
     (xclang--parse-output)))
 
+(defun xclang-definition ()
+  (interactive)
+  (xclang--goto "goto-definition"))
+
+(defun xclang-declaration ()
+  (interactive)
+  (xclang--goto "goto-declaration"))
+
+(defun xclang-implementation ()
+  (interactive)
+  (xclang--goto "goto-implementation"))
+
 (provide 'xclang-definition)
 ;;; xclang-definition.el ends here
@@ -0,0 +1,98 @@
+use std::{hash, os};
+use std::io;
+use std::io::fs;
+
+use rclang::compilation_database::CompilationCommand;
+use rclang::translation_unit::{TranslationUnit, ParseOptions};
+
+/**
+ * AST Cache
+ **/
+
+// Translation units saved between invocations, keyed by the file, its compilation
+// command and parse options, so changed build settings produce a new entry
+pub struct AstCache {
+    directory: Path
+}
+
+impl AstCache {
+    pub fn new(directory: Path) -> AstCache {
+        AstCache { directory: directory }
+    }
+
+    pub fn default() -> AstCache {
+        AstCache::new(os::tmpdir().join("xclang-ast-cache"))
+    }
+
+    pub fn path_for(&self, file_path: &Path, command: &CompilationCommand, options: &ParseOptions) -> Path {
+        let key = (file_path.as_str().unwrap_or(""), command.cwd.as_slice(),
+                   command.compiler.as_slice(), command.args.as_slice(), options.bits());
+        self.directory.join(format!("{:x}.ast", hash::hash(&key)))
+    }
+
+    // Saved AST is stale once file or any of included headers is modified after
+    // it was written. Missing headers are skipped, SDK paths recorded by another
+    // Xcode don't exist on the machine
+    pub fn load(&self, file_path: &Path, command: &CompilationCommand,
+                options: &ParseOptions) -> Option<TranslationUnit> {
+        let ast_path = self.path_for(file_path, command, options);
+        let (ast_stat, file_stat) = match (fs::stat(&ast_path), fs::stat(file_path)) {
+            (Ok(ast_stat), Ok(file_stat)) => (ast_stat, file_stat),
+            _ => return None
+        };
+        if ast_stat.modified < file_stat.modified { return None; }
+
+        let tu = match TranslationUnit::from_ast_file(&ast_path) { Some(tu) => tu, None => return None };
+        let modified_header = tu.included_files().iter().any(|header| {
+            match fs::stat(header) {
+                Ok(header_stat) => ast_stat.modified < header_stat.modified,
+                Err(_) => false
+            }
+        });
+        if modified_header { None } else { Some(tu) }
+    }
+
+    pub fn store(&self, tu: &TranslationUnit, file_path: &Path,
+                 command: &CompilationCommand, options: &ParseOptions) -> Result<(), ~str> {
+        if !self.directory.exists() {
+            match fs::mkdir_recursive(&self.directory, io::UserRWX) {
+                Ok(_) => (),
+                Err(e) => return Err(format!("Unable to create AST cache: {}", e))
+            }
+        }
+
+        match tu.save(&self.path_for(file_path, command, options)) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Unable to save AST: {}", e as int))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::os;
+    use rclang::compilation_database::CompilationCommand;
+    use rclang::translation_unit::ParseOptions;
+    use rclang::types::CXTranslationUnit_DetailedPreprocessingRecord;
+    use ast_cache::AstCache;
+
+    fn command(args: Vec<~str>) -> CompilationCommand {
+        CompilationCommand { cwd: ~"/foo", compiler: ~"clang", args: args }
+    }
+
+    #[test]
+    fn path_for_command() {
+        let cache = AstCache::new(os::tmpdir().join("xclang-ast-cache-test"));
+        let file = Path::new("/foo/AppDelegate.m");
+        let options = ParseOptions::new();
+        let debug = cache.path_for(&file, &command(vec!(~"-DDEBUG=1")), &options);
+
+        assert!(debug == cache.path_for(&file, &command(vec!(~"-DDEBUG=1")), &options));
+        assert!(debug != cache.path_for(&file, &command(vec!(~"-DDEBUG=0")), &options));
+        assert!(debug != cache.path_for(&Path::new("/foo/TestClass.m"), &command(vec!(~"-DDEBUG=1")), &options));
+
+        let detailed = options.with(CXTranslationUnit_DetailedPreprocessingRecord);
+        assert!(debug != cache.path_for(&file, &command(vec!(~"-DDEBUG=1")), &detailed));
+    }
+}
@@ -0,0 +1,111 @@
+use libc::c_uint;
+
+use rclang::cursor::Cursor;
+use rclang::source_location::{SourceLocation, SourceRange};
+use rclang::translation_unit::TranslationUnit;
+use rclang::types::*;
+
+/**
+ * Call Hierarchy
+ **/
+
+pub struct CallEntry {
+    pub name:      ~str,
+    pub usr:       ~str,
+    // Declaration of the caller or callee
+    pub location:  SourceLocation,
+    pub call_site: SourceRange
+}
+
+pub fn is_callable(kind: c_uint) -> bool {
+    match kind {
+        CXCursor_FunctionDecl | CXCursor_ObjCInstanceMethodDecl | CXCursor_ObjCClassMethodDecl => true,
+        _ => false
+    }
+}
+
+fn is_call(kind: c_uint) -> bool {
+    kind == CXCursor_CallExpr || kind == CXCursor_ObjCMessageExpr
+}
+
+// Methods are shown with their class, ex: `-[AppDelegate application:didFinishLaunchingWithOptions:]`
+pub fn display_name(callable: &Cursor) -> ~str {
+    let prefix = match callable.kind() {
+        CXCursor_ObjCInstanceMethodDecl => "-",
+        CXCursor_ObjCClassMethodDecl => "+",
+        _ => return callable.spelling()
+    };
+    prefix + "[" + callable.semantic_parent().spelling() + " " + callable.spelling() + "]"
+}
+
+// Function or method called at point, or the one containing point
+pub fn callable_at(tu: &TranslationUnit, file_path: &Path, line: uint, column: uint) -> Option<Cursor> {
+    let range = tu.range_at(file_path, (line, column), (line, column));
+    let cursors = tu.enclosing_cursors(&range);
+
+    match cursors.last() {
+        Some(cursor) if is_call(cursor.kind()) || cursor.kind() == CXCursor_DeclRefExpr => {
+            let referenced = cursor.referenced();
+            if is_callable(referenced.kind()) { return Some(referenced); }
+        },
+        _ => ()
+    }
+    cursors.iter().rev().find(|cursor| is_callable(cursor.kind())).map(|cursor| *cursor)
+}
+
+fn collect_calls(cursor: &Cursor, calls: &mut Vec<Cursor>) {
+    for child in cursor.children().iter() {
+        if is_call(child.kind()) { calls.push(*child); }
+        collect_calls(child, calls);
+    }
+}
+
+// Calls made from the body of the callable, it should be defined in the translation unit
+pub fn outgoing_calls(callable: &Cursor) -> Vec<CallEntry> {
+    let definition = callable.definition();
+    if definition.is_null() { return Vec::new(); }
+
+    let mut calls = Vec::new();
+    collect_calls(&definition, &mut calls);
+
+    calls.iter().filter_map(|call| {
+        let callee = call.referenced();
+        if callee.is_null() { return None; }
+        Some(CallEntry { name: display_name(&callee), usr: callee.usr(),
+                         location: callee.location(), call_site: call.extent() })
+    }).collect()
+}
+
+// Functions and methods defined in the main file of the translation unit
+pub fn definitions_in(tu: &TranslationUnit, file_path: &Path) -> Vec<Cursor> {
+    let file_name = file_path.as_str().unwrap_or("");
+    let mut definitions = Vec::new();
+    for cursor in tu.cursor().children().iter() {
+        if cursor.location().file.as_slice() != file_name { continue; }
+
+        if is_callable(cursor.kind()) && cursor.is_definition() { definitions.push(*cursor); }
+        for child in cursor.children().iter() {
+            if is_callable(child.kind()) && child.is_definition() { definitions.push(*child); }
+        }
+    }
+    definitions
+}
+
+pub fn definition_in(tu: &TranslationUnit, file_path: &Path, usr: &str) -> Option<Cursor> {
+    definitions_in(tu, file_path).move_iter().find(|definition| definition.usr().as_slice() == usr)
+}
+
+// Call sites of the callable with USR in the main file of the translation unit
+pub fn incoming_calls(tu: &TranslationUnit, file_path: &Path, usr: &str) -> Vec<CallEntry> {
+    let mut entries = Vec::new();
+    for caller in definitions_in(tu, file_path).iter() {
+        let mut calls = Vec::new();
+        collect_calls(caller, &mut calls);
+
+        for call in calls.iter().filter(|call| call.referenced().usr().as_slice() == usr) {
+            entries.push(CallEntry { name: display_name(caller), usr: caller.usr(),
+                                     location: caller.location(), call_site: call.extent() });
+        }
+    }
+    entries
+}
@@ -0,0 +1,178 @@
+use std::{hash, os};
+use std::io;
+use std::io::fs;
+use serialize::{json, Encodable};
+use time;
+
+/**
+ * Completion Cache
+ **/
+
+#[deriving(Clone, Encodable)]
+pub struct CachedCompletion {
+    pub typed_text:  ~str,
+    pub snippet:     ~str,
+    pub result_type: ~str,
+    pub comment:     ~str,
+    pub priority:    uint
+}
+
+#[deriving(Encodable)]
+struct CacheEntry {
+    file:        ~str,
+    line:        uint,
+    column:      uint,
+    version:     ~str,
+    created:     i64,
+    completions: Vec<CachedCompletion>
+}
+
+// Included headers aren't part of the version, so entries expire to pick up
+// changes made to them while the buffer stays the same
+static ENTRY_TTL: i64 = 60;
+
+// Buffer contents without the prefix being typed, so version stays the same
+// while prefix grows. Salt separates results requested with different options
+pub fn buffer_version(contents: &str, start_offset: uint, prefix_len: uint, salt: &str) -> u64 {
+    let end_offset = if start_offset + prefix_len > contents.len() { contents.len() } else { start_offset + prefix_len };
+    hash::hash(&(contents.slice_to(start_offset), contents.slice_from(end_offset), salt))
+}
+
+// Keeps only the last result set for each file
+pub struct CompletionCache {
+    directory: Path
+}
+
+impl CompletionCache {
+    pub fn new(directory: Path) -> CompletionCache {
+        CompletionCache { directory: directory }
+    }
+
+    pub fn default() -> CompletionCache {
+        CompletionCache::new(os::tmpdir().join("xclang-completion-cache"))
+    }
+
+    fn entry_path(&self, file_path: &Path) -> Path {
+        let file_hash = hash::hash(&file_path.as_str().unwrap_or(""));
+        self.directory.join(format!("{:x}.json", file_hash))
+    }
+
+    pub fn load(&self, file_path: &Path, line: uint, column: uint, version: u64) -> Option<Vec<CachedCompletion>> {
+        let mut file = match fs::File::open(&self.entry_path(file_path)) {
+            Ok(f) => f,
+            Err(_) => return None
+        };
+        let entry = match json::from_reader(&mut file as &mut Reader) {
+            Ok(entry) => entry,
+            Err(_) => return None
+        };
+
+        let string_field = |object: &json::Json, name: &str| -> ~str {
+            match object.find(&name.to_owned()).and_then(|value| value.as_string()) {
+                Some(value) => value.to_owned(),
+                None => ~""
+            }
+        };
+        let number_field = |object: &json::Json, name: &str| -> uint {
+            match object.find(&name.to_owned()).and_then(|value| value.as_number()) {
+                Some(value) => value as uint,
+                None => 0
+            }
+        };
+
+        if string_field(&entry, "file") != file_path.as_str().unwrap_or("").to_owned() ||
+            number_field(&entry, "line") != line || number_field(&entry, "column") != column ||
+            string_field(&entry, "version") != version.to_str() {
+            return None;
+        }
+
+        let created = match entry.find(&~"created").and_then(|value| value.as_number()) {
+            Some(value) => value as i64,
+            None => return None
+        };
+        if time::get_time().sec - created > ENTRY_TTL { return None; }
+
+        match entry.find(&~"completions").and_then(|value| value.as_list()) {
+            Some(completions) => Some(completions.iter().map(|completion| {
+                CachedCompletion {
+                    typed_text:  string_field(completion, "typed_text"),
+                    snippet:     string_field(completion, "snippet"),
+                    result_type: string_field(completion, "result_type"),
+                    comment:     string_field(completion, "comment"),
+                    priority:    number_field(completion, "priority")
+                }
+            }).collect()),
+            None => None
+        }
+    }
+
+    pub fn store(&self, file_path: &Path, line: uint, column: uint, version: u64,
+                 completions: Vec<CachedCompletion>) -> Result<(), ~str> {
+        if !self.directory.exists() {
+            match fs::mkdir_recursive(&self.directory, io::UserRWX) {
+                Ok(_) => (),
+                Err(e) => return Err(format!("Unable to create completion cache: {}", e))
+            }
+        }
+
+        let entry = CacheEntry {
+            file:        file_path.as_str().unwrap_or("").to_owned(),
+            line:        line,
+            column:      column,
+            version:     version.to_str(),
+            created:     time::get_time().sec,
+            completions: completions
+        };
+
+        let encoding_result = match fs::File::create(&self.entry_path(file_path)) {
+            Ok(f) => {
+                let mut f2 = f;
+                let encoder = &mut json::Encoder::new(&mut f2 as &mut Writer);
+                entry.encode(encoder)
+            },
+            Err(e) => return Err(format!("Unable to write completion cache: {}", e))
+        };
+
+        match encoding_result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Unable to write completion cache: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::TempDir;
+    use completion_cache::{CompletionCache, CachedCompletion, buffer_version};
+
+    #[test]
+    fn version_ignores_prefix() {
+        let version = buffer_version("[TestClass pre]", 11, 3, "");
+        assert!(version == buffer_version("[TestClass prepare]", 11, 7, ""));
+        assert!(version != buffer_version("[TestClass pre];", 11, 3, ""));
+        assert!(version != buffer_version("[TestClass pre]", 11, 3, "lsp"));
+    }
+
+    #[test]
+    fn store_and_load() {
+        let directory = TempDir::new("xclang-completion-cache").unwrap();
+        let cache = CompletionCache::new(directory.path().clone());
+        let file = Path::new("/foo/AppDelegate.m");
+        let completion = CachedCompletion {
+            typed_text:  ~"prepareToTest",
+            snippet:     ~"prepareToTest",
+            result_type: ~"void",
+            comment:     ~"",
+            priority:    37
+        };
+        assert!(cache.store(&file, 16, 16, 42, vec!(completion)).is_ok());
+
+        let completions = cache.load(&file, 16, 16, 42).unwrap();
+        assert!(completions.len() == 1);
+        assert!(completions.get(0).typed_text == ~"prepareToTest");
+        assert!(completions.get(0).priority == 37);
+        assert!(cache.load(&file, 16, 16, 43).is_none());
+        assert!(cache.load(&file, 17, 16, 42).is_none());
+    }
+}
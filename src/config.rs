@@ -0,0 +1,343 @@
+use std::io::fs::File;
+
+use rclang::code_completion::SnippetFormat;
+use rclang::types::*;
+use rclang::argument_rewriter::{ArgumentRewriter, AddFlag, RemoveFlag, RemapPathPrefix};
+
+/**
+ * Project Configuration
+ **/
+
+pub enum WarningPolicy {
+    ShowWarnings,
+    HideWarnings,
+    WarningsAsErrors
+}
+
+pub enum OutputFormat {
+    TextOutput,
+    JsonOutput
+}
+
+pub struct FileFlags {
+    pub path:         ~str,
+    pub extra_flags:  Vec<~str>,
+    pub remove_flags: Vec<~str>
+}
+
+pub struct Config {
+    pub directory:     Path,
+    pub workspace:     Option<~str>,
+    pub project:       Option<~str>,
+    pub scheme:        Option<~str>,
+    pub sdk:           Option<~str>,
+    pub configuration: Option<~str>,
+    pub extra_flags:   Vec<~str>,
+    pub remove_flags:  Vec<~str>,
+    pub exclude:       Vec<~str>,
+    pub file_flags:    Vec<FileFlags>,
+    pub path_mappings: Vec<(~str, ~str)>,
+    pub completion_limit: Option<uint>,
+    pub snippet_format: Option<SnippetFormat>,
+    pub completion_flags: Vec<CXCodeComplete_Flags>,
+    pub parse_flags:   Vec<CXTranslationUnit_Flags>,
+    pub warnings:      WarningPolicy,
+    pub format:        OutputFormat
+}
+
+pub fn parse_completion_flag(name: &str) -> Result<CXCodeComplete_Flags, ~str> {
+    match name {
+        "macros"        => Ok(CXCodeComplete_IncludeMacros),
+        "patterns"      => Ok(CXCodeComplete_IncludeCodePatterns),
+        "comments"      => Ok(CXCodeComplete_IncludeBriefComments),
+        "skip-preamble" => Ok(CXCodeComplete_SkipPreamble),
+        "fixits"        => Ok(CXCodeComplete_IncludeCompletionsWithFixIts),
+        _ => Err(format!("Unknown completion option: {}", name))
+    }
+}
+
+pub fn parse_translation_unit_flag(name: &str) -> Result<CXTranslationUnit_Flags, ~str> {
+    match name {
+        "detailed-preprocessing-record"  => Ok(CXTranslationUnit_DetailedPreprocessingRecord),
+        "incomplete"                     => Ok(CXTranslationUnit_Incomplete),
+        "skip-function-bodies"           => Ok(CXTranslationUnit_SkipFunctionBodies),
+        "create-preamble-on-first-parse" => Ok(CXTranslationUnit_CreatePreambleOnFirstParse),
+        "keep-going"                     => Ok(CXTranslationUnit_KeepGoing),
+        "single-file-parse"              => Ok(CXTranslationUnit_SingleFileParse),
+        "limit-skip-function-bodies"     => Ok(CXTranslationUnit_LimitSkipFunctionBodiesToPreamble),
+        "ignore-included-warnings"       => Ok(CXTranslationUnit_IgnoreNonErrorsFromIncludedFiles),
+        _ => Err(format!("Unknown parse option: {}", name))
+    }
+}
+
+enum Value {
+    StringValue(~str),
+    NumberValue(uint),
+    ListValue(Vec<~str>)
+}
+
+fn parse_string(value: &str) -> Result<~str, ~str> {
+    if value.len() < 2 || !value.starts_with("\"") || !value.ends_with("\"") {
+        return Err(format!("Expected quoted string, found: {}", value));
+    }
+    Ok(value.slice(1, value.len() - 1).to_owned())
+}
+
+// Comment markers and list separators are only recognized outside of quoted strings
+fn unquoted_positions(value: &str, separator: char) -> Vec<uint> {
+    let mut positions = Vec::new();
+    let mut quoted = false;
+    for (idx, c) in value.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            positions.push(idx);
+        }
+    }
+    positions
+}
+
+fn strip_comment<'a>(line: &'a str) -> &'a str {
+    match unquoted_positions(line, '#').as_slice().get(0) {
+        Some(&idx) => line.slice_to(idx).trim(),
+        None => line
+    }
+}
+
+fn split_items<'a>(list: &'a str) -> Vec<&'a str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    for &idx in unquoted_positions(list, ',').iter() {
+        items.push(list.slice(start, idx));
+        start = idx + 1;
+    }
+    items.push(list.slice_from(start));
+    items
+}
+
+fn parse_value(value: &str) -> Result<Value, ~str> {
+    match from_str::<uint>(value) { Some(number) => return Ok(NumberValue(number)), None => () };
+    if !value.starts_with("[") { return Ok(StringValue(try!(parse_string(value)))); }
+    if !value.ends_with("]") { return Err(format!("Arrays should be on a single line: {}", value)); }
+
+    let mut items = Vec::new();
+    for item in split_items(value.slice(1, value.len() - 1)).iter() {
+        let trimmed = item.trim();
+        if trimmed.len() == 0 { continue; }
+        items.push(try!(parse_string(trimmed)));
+    }
+    Ok(ListValue(items))
+}
+
+impl Config {
+    pub fn new(directory: Path) -> Config {
+        Config {
+            directory:     directory,
+            workspace:     None,
+            project:       None,
+            scheme:        None,
+            sdk:           None,
+            configuration: None,
+            extra_flags:   Vec::new(),
+            remove_flags:  Vec::new(),
+            exclude:       Vec::new(),
+            file_flags:    Vec::new(),
+            path_mappings: Vec::new(),
+            completion_limit: None,
+            snippet_format: None,
+            completion_flags: Vec::new(),
+            parse_flags:   Vec::new(),
+            warnings:      ShowWarnings,
+            format:        TextOutput
+        }
+    }
+
+    // Supports subset of toml used by xclang: `key = "value"`, `key = ["a", "b"]`
+    // and `[files."path"]` tables with flags for the files inside path
+    pub fn parse(directory: Path, contents: &str) -> Result<Config, ~str> {
+        let mut config = Config::new(directory);
+
+        for (idx, raw_line) in contents.lines().enumerate() {
+            let line = strip_comment(raw_line.trim());
+            if line.len() == 0 { continue; }
+
+            if line.starts_with("[") {
+                if !line.starts_with("[files.") || !line.ends_with("]") {
+                    return Err(format!("Unknown table on line {}: {}", idx + 1, line));
+                }
+                let path = line.slice(7, line.len() - 1).trim();
+                let path = if path.starts_with("\"") { try!(parse_string(path)) } else { path.to_owned() };
+                config.file_flags.push(FileFlags { path: path, extra_flags: Vec::new(), remove_flags: Vec::new() });
+                continue;
+            }
+
+            let separator = match line.find('=') {
+                Some(s) => s,
+                None => return Err(format!("Invalid line {}: {}", idx + 1, line))
+            };
+            let key = line.slice_to(separator).trim();
+            let value = try!(parse_value(line.slice_from(separator + 1).trim()));
+
+            if config.file_flags.len() > 0 {
+                let file_flags = config.file_flags.mut_last().unwrap();
+                match (key, value) {
+                    ("extra_flags", ListValue(v))  => file_flags.extra_flags = v,
+                    ("remove_flags", ListValue(v)) => file_flags.remove_flags = v,
+                    _ => return Err(format!("Unknown or invalid file key on line {}: {}", idx + 1, key))
+                }
+                continue;
+            }
+
+            match (key, value) {
+                ("workspace", StringValue(v))     => config.workspace = Some(v),
+                ("project", StringValue(v))       => config.project = Some(v),
+                ("scheme", StringValue(v))        => config.scheme = Some(v),
+                ("sdk", StringValue(v))           => config.sdk = Some(v),
+                ("configuration", StringValue(v)) => config.configuration = Some(v),
+                ("extra_flags", ListValue(v))     => config.extra_flags = v,
+                ("remove_flags", ListValue(v))    => config.remove_flags = v,
+                ("exclude", ListValue(v))         => config.exclude = v,
+                ("completion_limit", NumberValue(v)) => config.completion_limit = Some(v),
+                ("path_mappings", ListValue(v)) => {
+                    for mapping in v.iter() {
+                        match mapping.find('=') {
+                            Some(s) => config.path_mappings.push((mapping.slice_to(s).to_owned(),
+                                                                  mapping.slice_from(s + 1).to_owned())),
+                            None => return Err(format!("Path mapping should be in format from=to: {}", mapping))
+                        }
+                    }
+                },
+                ("warnings", StringValue(v)) => {
+                    config.warnings = match v.as_slice() {
+                        "show"  => ShowWarnings,
+                        "hide"  => HideWarnings,
+                        "error" => WarningsAsErrors,
+                        _ => return Err(format!("Unknown warnings policy: {}", v))
+                    }
+                },
+                ("completion_options", ListValue(v)) => {
+                    for name in v.iter() {
+                        config.completion_flags.push(try!(parse_completion_flag(name.as_slice())));
+                    }
+                },
+                ("parse_options", ListValue(v)) => {
+                    for name in v.iter() {
+                        config.parse_flags.push(try!(parse_translation_unit_flag(name.as_slice())));
+                    }
+                },
+                ("snippet_format", StringValue(v)) => {
+                    config.snippet_format = match SnippetFormat::from_str(v) {
+                        Some(format) => Some(format),
+                        None => return Err(format!("Unknown snippet format: {}", v))
+                    }
+                },
+                ("format", StringValue(v)) => {
+                    config.format = match v.as_slice() {
+                        "text" => TextOutput,
+                        "json" => JsonOutput,
+                        _ => return Err(format!("Unknown output format: {}", v))
+                    }
+                },
+                _ => return Err(format!("Unknown or invalid key on line {}: {}", idx + 1, key))
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn from_file(file_path: &Path) -> Result<Config, ~str> {
+        let contents = match File::open(file_path).read_to_str() {
+            Ok(c) => c,
+            Err(e) => return Err(format!("Unable to read {}: {}", file_path.display(), e))
+        };
+
+        Config::parse(file_path.dir_path(), contents)
+    }
+
+    pub fn find(file_path: &Path) -> Result<Option<Config>, ~str> {
+        let mut path = file_path.clone();
+        loop {
+            let config_path = path.join(".xclang.toml");
+            if config_path.exists() { return Config::from_file(&config_path).map(|c| Some(c)); }
+            if !path.pop() { break; }
+        }
+
+        Ok(None)
+    }
+
+    pub fn argument_rewriter(&self) -> ArgumentRewriter {
+        let mut rewriter = ArgumentRewriter::with_default_rules();
+        for &(ref from, ref to) in self.path_mappings.iter() {
+            rewriter.add_rule(RemapPathPrefix(from.clone(), to.clone()));
+        }
+        for flag in self.remove_flags.iter() { rewriter.add_rule(RemoveFlag(flag.clone())); }
+        for flag in self.extra_flags.iter() { rewriter.add_rule(AddFlag(flag.clone())); }
+
+        for file_flags in self.file_flags.iter() {
+            let scope = self.directory.join(file_flags.path.as_slice());
+            for flag in file_flags.remove_flags.iter() {
+                rewriter.add_scoped_rule(scope.clone(), RemoveFlag(flag.clone()));
+            }
+            for flag in file_flags.extra_flags.iter() {
+                rewriter.add_scoped_rule(scope.clone(), AddFlag(flag.clone()));
+            }
+        }
+
+        rewriter
+    }
+
+    pub fn is_excluded(&self, file_path: &Path) -> bool {
+        self.exclude.iter().any(|excluded| {
+            self.directory.join(excluded.as_slice()).is_ancestor_of(file_path)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use config::{Config, HideWarnings, JsonOutput};
+
+    #[test]
+    fn parse_config() {
+        let contents = r##"
+# xclang settings
+workspace = "TestApplication"
+sdk = "iphonesimulator"
+extra_flags = ["-DXCLANG=1", "-Wno-unused", "-Wl,-rpath"]
+remove_flags = []
+exclude = ["Pods"]
+path_mappings = ["/Applications/Xcode.app/Contents/Developer=/opt/xcode"]
+warnings = "hide"  # show, hide or error
+format = "json"    # text or json
+completion_limit = 50
+snippet_format = "yas"  # yas or lsp
+parse_options = ["skip-function-bodies", "keep-going"]
+
+[files."Pods"]  # third party code
+extra_flags = ["-Wno-everything", "-DTAG=#pods"]
+"##;
+        let config = Config::parse(Path::new("/foo"), contents).unwrap();
+
+        assert!(config.workspace == Some(~"TestApplication"));
+        assert!(config.scheme.is_none());
+        assert!(config.extra_flags == vec!(~"-DXCLANG=1", ~"-Wno-unused", ~"-Wl,-rpath"));
+        assert!(config.remove_flags.len() == 0);
+        assert!(config.completion_limit == Some(50));
+        assert!(config.parse_flags.len() == 2);
+        assert!(match config.warnings { HideWarnings => true, _ => false });
+        assert!(match config.format { JsonOutput => true, _ => false });
+        assert!(config.path_mappings == vec!((~"/Applications/Xcode.app/Contents/Developer", ~"/opt/xcode")));
+        assert!(config.file_flags.len() == 1);
+        assert!(config.file_flags.get(0).path == ~"Pods");
+        assert!(config.file_flags.get(0).extra_flags == vec!(~"-Wno-everything", ~"-DTAG=#pods"));
+        assert!(config.is_excluded(&Path::new("/foo/Pods/AFNetworking/AFHTTPClient.m")));
+        assert!(!config.is_excluded(&Path::new("/foo/TestApplication/AppDelegate.m")));
+    }
+
+    #[test]
+    fn parse_invalid_config() {
+        assert!(Config::parse(Path::new("/foo"), "workspace = TestApplication").is_err());
+        assert!(Config::parse(Path::new("/foo"), "unknown = \"value\"").is_err());
+    }
+}
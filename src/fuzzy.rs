@@ -0,0 +1,76 @@
+/**
+ * Fuzzy matching for completion candidates
+ **/
+
+static START_BONUS: uint = 8;
+static BOUNDARY_BONUS: uint = 6;
+static CONSECUTIVE_BONUS: uint = 4;
+static MATCH_SCORE: uint = 1;
+
+// Start of camelCase hump or selector piece, ex: `W` in `initWithFrame:`
+fn is_boundary(candidate: &[char], idx: uint) -> bool {
+    if idx == 0 { return true; }
+    let previous = candidate[idx - 1];
+    candidate[idx].is_uppercase() && !previous.is_uppercase() ||
+        previous == ':' || previous == '_'
+}
+
+// Returns score for pattern as a case-insensitive subsequence of candidate,
+// higher is better, `None` when pattern doesn't match. Each matched character
+// starts a hump or selector piece or follows the previous match, so short
+// prefixes don't match letters scattered over long selectors
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<uint> {
+    let pattern_chars: Vec<char> = pattern.chars().map(|c| c.to_lowercase()).collect();
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+
+    let mut score = 0;
+    let mut pattern_idx = 0;
+    let mut previous_match: Option<uint> = None;
+    for (idx, &c) in candidate_chars.iter().enumerate() {
+        if pattern_idx == pattern_chars.len() { break; }
+        if c.to_lowercase() != *pattern_chars.get(pattern_idx) { continue; }
+
+        let consecutive = idx > 0 && previous_match == Some(idx - 1);
+        let boundary = is_boundary(candidate_chars.as_slice(), idx);
+        if !consecutive && !boundary { continue; }
+
+        score += MATCH_SCORE;
+        if idx == 0 { score += START_BONUS; }
+        else if boundary { score += BOUNDARY_BONUS; }
+        if consecutive { score += CONSECUTIVE_BONUS; }
+
+        previous_match = Some(idx);
+        pattern_idx += 1;
+    }
+
+    if pattern_idx == pattern_chars.len() { Some(score) } else { None }
+}
+
+#[cfg(test)]
+mod test {
+
+    use fuzzy::fuzzy_score;
+
+    #[test]
+    fn subsequence_match() {
+        assert!(fuzzy_score("", "init").is_some());
+        assert!(fuzzy_score("iwf", "initWithFrame:").is_some());
+        assert!(fuzzy_score("frame", "initWithFrame:").is_some());
+        assert!(fuzzy_score("init", "alloc").is_none());
+        assert!(fuzzy_score("pre", "performSelector:").is_none());
+        assert!(fuzzy_score("init", "reinitialize").is_none());
+    }
+
+    #[test]
+    fn ranking() {
+        let prefix = fuzzy_score("init", "init").unwrap();
+        let humps = fuzzy_score("iwf", "initWithFrame:").unwrap();
+        let scattered = fuzzy_score("iwf", "isViewLoadedWithoutFrame").unwrap();
+        let start = fuzzy_score("frame", "frame").unwrap();
+        let inner = fuzzy_score("frame", "initWithFrame:").unwrap();
+
+        assert!(prefix > humps);
+        assert!(start > inner);
+        assert!(humps > scattered);
+    }
+}
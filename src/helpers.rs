@@ -1,54 +1,778 @@
 use std::io::fs;
+use libc::c_uint;
+use time;
+
+use serialize::json;
 
 use rclang::compilation_database::CompilationDatabase;
-use rclang::translation_unit::TranslationUnit;
+use rclang::translation_unit::{TranslationUnit, ParseOptions};
+use rclang::token::Token;
+use rclang::cursor::Cursor;
+use rclang::diagnostic::Diagnostic;
+use rclang::source_file::{SourceFile, ColumnEncoding};
+use rclang::source_location::SourceRange;
+use rclang::code_completion::{SnippetFormat, YasSnippet};
 use rclang::types::*;
 
+use fuzzy::fuzzy_score;
+use ast_cache::AstCache;
+use completion_cache::{CompletionCache, CachedCompletion, buffer_version};
+use call_hierarchy::{CallEntry, callable_at, definition_in};
+use call_hierarchy;
+use overrides::{method_at, super_methods, overrides_in};
+use navigation::{Locator, symbol_at, message_at, declarations_of, definitions_of, implementations_in};
+use type_hierarchy::{TypeEntry, TypeRelations, type_at};
+use type_hierarchy;
+use signature_help::{enclosing_call, FunctionCall, MessageSend};
+use config::{Config, HideWarnings, WarningsAsErrors, TextOutput, JsonOutput};
+
+#[deriving(Encodable)]
+struct DiagnosticData {
+    file:     ~str,
+    line:     uint,
+    column:   uint,
+    severity: ~str,
+    message:  ~str
+}
+
 pub fn syntax_check(original_file: &Path, input_file: &Path) -> Result<StrBuf, ~str> {
+    let config = try!(config_for(original_file));
+    if config.is_excluded(original_file) { return Ok(StrBuf::new()); }
+
     let tu = try!(tu_for(original_file, input_file));
+    return Ok(format_diagnostics(tu.diagnostics().as_slice(), &config));
+}
+
+// Only severity following the `file:line:column: ` prefix is replaced, message
+// text may contain `warning: ` too
+fn promote_to_error(diagnostic: &Diagnostic) -> ~str {
+    let location = format!("{}:{}:{}: ", diagnostic.location.file, diagnostic.location.line,
+                           diagnostic.location.column);
+    let formatted = diagnostic.formatted.as_slice();
+    let start = if formatted.starts_with(location.as_slice()) { location.len() } else { 0 };
+    if !formatted.slice_from(start).starts_with("warning: ") { return formatted.to_owned(); }
 
+    formatted.slice_to(start).to_owned() + "error: " + formatted.slice_from(start + "warning: ".len())
+}
+
+fn format_diagnostics(diagnostics: &[Diagnostic], config: &Config) -> StrBuf {
     let mut output = StrBuf::new();
-    for diagnostic in tu.diagnostics().iter() {
-        output.push_str(diagnostic.formatted + "\n");
+    let mut json_diagnostics = Vec::new();
+    for diagnostic in diagnostics.iter() {
+        let is_warning = diagnostic.severity == CXDiagnostic_Warning;
+        let (formatted, severity) = match config.warnings {
+            HideWarnings if is_warning => continue,
+            WarningsAsErrors if is_warning => (promote_to_error(diagnostic), CXDiagnostic_Error),
+            _ => (diagnostic.formatted.clone(), diagnostic.severity)
+        };
+
+        match config.format {
+            TextOutput => output.push_str(formatted + "\n"),
+            JsonOutput => json_diagnostics.push(DiagnosticData {
+                file:     diagnostic.location.file.clone(),
+                line:     diagnostic.location.line,
+                column:   diagnostic.location.column,
+                severity: severity_name(severity),
+                message:  diagnostic.spelling.clone()
+            })
+        }
+    }
+
+    match config.format {
+        JsonOutput => output.push_str(json::Encoder::str_encode(&json_diagnostics) + "\n"),
+        TextOutput => ()
+    }
+    output
+}
+
+fn severity_name(severity: CXDiagnosticSeverity) -> ~str {
+    match severity {
+        CXDiagnostic_Ignored => ~"ignored",
+        CXDiagnostic_Note    => ~"note",
+        CXDiagnostic_Warning => ~"warning",
+        CXDiagnostic_Error   => ~"error",
+        CXDiagnostic_Fatal   => ~"fatal"
+    }
+}
+
+pub struct CompletionOptions {
+    pub prefix: ~str,
+    pub limit:  Option<uint>,
+    pub format: Option<SnippetFormat>,
+    pub flags:  Vec<CXCodeComplete_Flags>,
+    pub cache:  bool,
+    // Defaults to the shared directory in tmp
+    pub cache_directory: Option<Path>
+}
+
+impl CompletionOptions {
+    pub fn new(prefix: ~str) -> CompletionOptions {
+        CompletionOptions { prefix: prefix, limit: None, format: None, flags: Vec::new(), cache: true,
+                            cache_directory: None }
     }
+}
+
+// Results are requested at the start of the prefix and cached until buffer
+// changes outside of the prefix, so extending the prefix only re-filters them
+pub fn code_completion(original_file: &Path, input_file: &Path, location: &str,
+                       options: &CompletionOptions) -> Result<StrBuf, ~str> {
+    let config = try!(config_for(original_file));
+    let (line, column) = try!(parse_location(location));
+
+    let prefix = options.prefix.as_slice();
+    let start_column = column - prefix.len() + 1;
+    let flags: Vec<CXCodeComplete_Flags> = options.flags.iter().chain(config.completion_flags.iter())
+                                                   .map(|&flag| flag).collect();
+    let format = options.format.or(config.snippet_format).unwrap_or(YasSnippet);
+
+    // Brief comments have to be requested at parse time too
+    let parse_flags = if flags.iter().any(|&flag| flag as u32 == CXCodeComplete_IncludeBriefComments as u32) {
+        vec!(CXTranslationUnit_IncludeBriefCommentsInCodeCompletion)
+    } else {
+        Vec::new()
+    };
+    let parse_options = parse_options_for(&config, parse_flags.as_slice());
+    let c_db = try!(c_db_for(original_file));
+    let c_data = match c_db.compilation_command_for(original_file) {
+        Some(c_data) => c_data,
+        None => return Err(~"Unable to find compilation command in the database")
+    };
+
+    let cache = match options.cache_directory {
+        Some(ref directory) => CompletionCache::new(directory.clone()),
+        None => CompletionCache::default()
+    };
+    let source = try!(SourceFile::open(input_file));
+    // Compilation command is already rewritten by config, so changes to flags
+    // and path mappings invalidate cached results too
+    let salt = flags.iter().fold((format as uint).to_str(), |salt, &flag| salt + "," + (flag as uint).to_str())
+        + ";" + parse_options.bits().to_str() + ";" + c_data.cwd + ";" + c_data.args.connect(" ");
+    let version = source.offset_for(line, start_column).map(|start| {
+        buffer_version(source.contents, start, prefix.len(), salt.as_slice())
+    });
+
+    let cached = match version {
+        Some(v) if options.cache => cache.load(original_file, line, start_column, v),
+        _ => None
+    };
+    let candidates = match cached {
+        Some(candidates) => candidates,
+        None => {
+            let tu = TranslationUnit::with_options(&c_data, input_file, &parse_options);
+            // libclang defaults are used unless completion options are requested
+            let completions = if flags.len() == 0 {
+                tu.complete_code_at(input_file, line, start_column)
+            } else {
+                tu.complete_code_with_flags_at(input_file, line, start_column, flags.as_slice())
+            };
+            let candidates: Vec<CachedCompletion> = completions.iter().filter(|&c| {
+                c.availability == CXAvailability_Available
+            }).map(|c| {
+                CachedCompletion {
+                    typed_text:  c.typed_text(),
+                    snippet:     c.to_snippet(format),
+                    result_type: c.result_type(),
+                    comment:     c.comment.clone(),
+                    priority:    c.priority
+                }
+            }).collect();
+
+            match version {
+                Some(v) if options.cache => {
+                    // Failing cache shouldn't break completion, results are just recomputed next time
+                    match cache.store(original_file, line, start_column, v, candidates.clone()) {
+                        Ok(_) => (),
+                        Err(e) => debug!("{}", e)
+                    }
+                },
+                _ => ()
+            }
+            candidates
+        }
+    };
+
+    let mut matches: Vec<(uint, &CachedCompletion)> = candidates.iter().filter_map(|c| {
+        fuzzy_score(prefix, c.typed_text).map(|score| (score, c))
+    }).collect();
+
+    // Better fuzzy matches first, clang priority (lower is better) breaks ties
+    matches.sort_by(|&(score_a, a), &(score_b, b)| {
+        match score_b.cmp(&score_a) {
+            Equal => a.priority.cmp(&b.priority),
+            ordering => ordering
+        }
+    });
+
+    let limit = options.limit.or(config.completion_limit).unwrap_or(matches.len());
+    let mut output = StrBuf::new();
+    for &(_, completion) in matches.iter().take(limit) {
+        output.push_str(completion.snippet + "\t" + completion.result_type);
+        if completion.comment.len() > 0 { output.push_str("\t" + completion.comment); }
+        output.push_str("\n");
+    };
 
     return Ok(output);
 }
 
-pub fn code_completion(original_file: &Path, input_file: &Path, location: &str, prefix: &str) -> Result<StrBuf, ~str> {
+static COMPLETION_CONTEXTS: &'static [(CXCompletionContext, &'static str)] = &[
+    (CXCompletionContext_AnyType, "any-type"),
+    (CXCompletionContext_AnyValue, "any-value"),
+    (CXCompletionContext_ObjCObjectValue, "objc-object-value"),
+    (CXCompletionContext_ObjCSelectorValue, "objc-selector-value"),
+    (CXCompletionContext_DotMemberAccess, "dot-member-access"),
+    (CXCompletionContext_ArrowMemberAccess, "arrow-member-access"),
+    (CXCompletionContext_ObjCPropertyAccess, "objc-property-access"),
+    (CXCompletionContext_EnumTag, "enum-tag"),
+    (CXCompletionContext_StructTag, "struct-tag"),
+    (CXCompletionContext_ObjCInterface, "objc-interface"),
+    (CXCompletionContext_ObjCProtocol, "objc-protocol"),
+    (CXCompletionContext_ObjCCategory, "objc-category"),
+    (CXCompletionContext_ObjCInstanceMessage, "objc-instance-message"),
+    (CXCompletionContext_ObjCClassMessage, "objc-class-message"),
+    (CXCompletionContext_ObjCSelectorName, "objc-selector-name"),
+    (CXCompletionContext_MacroName, "macro-name"),
+    (CXCompletionContext_NaturalLanguage, "natural-language")
+];
+
+pub fn completion_context(original_file: &Path, input_file: &Path, location: &str) -> Result<StrBuf, ~str> {
     let tu = try!(tu_for(original_file, input_file));
     let (line, column) = try!(parse_location(location));
+    let completions = tu.complete_code_at(input_file, line, column);
 
-    let completions = tu.complete_code_at(input_file, line, column - prefix.len() + 1);
-    let mut c_iterator = completions.iter().filter(|&c| {
-        c.availability == CXAvailability_Available
-    });
+    let contexts: Vec<&str> = COMPLETION_CONTEXTS.iter().filter(|&&(context, _)| {
+        completions.has_context(context)
+    }).map(|&(_, name)| name).collect();
+
+    let mut output = StrBuf::new();
+    output.push_str("contexts\t" + contexts.connect(",") + "\n");
+    output.push_str("container\t" + completions.container_kind_name + "\t" + completions.container_usr + "\n");
+    return Ok(output);
+}
+
+// Overload candidates carry the current parameter, otherwise candidates are found by
+// completing at the start of the function name or selector
+pub fn signature_help(original_file: &Path, input_file: &Path, location: &str) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let source = try!(SourceFile::open(input_file));
+
+    let mut signatures: Vec<(uint, ~str, uint, ~str)> = Vec::new();
+    for candidate in tu.complete_code_at(input_file, line, column).iter() {
+        match candidate.current_parameter() {
+            Some(active) => signatures.push((candidate.priority, candidate.signature(), active, candidate.parent.clone())),
+            None => ()
+        }
+    }
+
+    if signatures.len() == 0 {
+        let call = match source.offset_for(line, column).and_then(|offset| enclosing_call(source.contents, offset)) {
+            Some(call) => call,
+            None => return Ok(StrBuf::new())
+        };
+
+        // Without typed selector pieces every method of the receiver would match
+        if call.kind == MessageSend && call.name.len() == 0 { return Ok(StrBuf::new()); }
+
+        let (name_line, name_column) = match source.location_for(call.name_offset) {
+            Some(location) => location,
+            None => return Ok(StrBuf::new())
+        };
+        for candidate in tu.complete_code_at(input_file, name_line, name_column).iter() {
+            let typed_text = candidate.typed_text();
+            let matches = match call.kind {
+                FunctionCall => typed_text == call.name,
+                MessageSend => typed_text.starts_with(call.name.as_slice()) && typed_text.ends_with(":")
+            };
+            if matches {
+                signatures.push((candidate.priority, candidate.signature(), call.active_parameter, candidate.parent.clone()));
+            }
+        }
+    }
+
+    signatures.sort_by(|&(a, _, _, _), &(b, _, _, _)| a.cmp(&b));
+
+    let mut output = StrBuf::new();
+    for &(_, ref signature, active, ref parent) in signatures.iter() {
+        output.push_str(*signature + "\t" + active.to_str());
+        if parent.len() > 0 { output.push_str("\t" + *parent); }
+        output.push_str("\n");
+    }
+    return Ok(output);
+}
+
+// Parse time in milliseconds followed by memory usage of the translation unit in bytes
+pub fn stats(original_file: &Path, input_file: &Path,
+             flags: &[CXTranslationUnit_Flags]) -> Result<StrBuf, ~str> {
+    let start = time::precise_time_ns();
+    let tu = try!(tu_with_flags_for(original_file, input_file, flags));
+    let parse_time = (time::precise_time_ns() - start) / 1000000;
 
     let mut output = StrBuf::new();
-    for completion in c_iterator {
-        let result = completion.to_yas();
-        if result.contains(prefix) { output.push_str(result + "\n") }
+    output.push_str("parse-time\t" + parse_time.to_str() + "\n");
+
+    let usage = tu.resource_usage();
+    for entry in usage.iter() {
+        output.push_str(entry.name + "\t" + entry.amount.to_str() + "\n");
+    }
+    let total = usage.iter().fold(0, |total, entry| total + entry.amount);
+    output.push_str("total\t" + total.to_str() + "\n");
+    return Ok(output);
+}
+
+// Class of identifier based on the cursor it's annotated with, references are
+// classified by the declaration they point to
+fn semantic_class(token: &Token) -> Option<&'static str> {
+    if token.kind != CXToken_Identifier { return None; }
+
+    let kind = match token.cursor_kind as c_uint {
+        CXCursor_DeclRefExpr | CXCursor_MemberRefExpr => token.referenced_kind as c_uint,
+        kind => kind
     };
 
+    match kind {
+        CXCursor_ObjCInterfaceDecl | CXCursor_ObjCImplementationDecl | CXCursor_ObjCCategoryDecl |
+        CXCursor_ObjCCategoryImplDecl | CXCursor_ObjCClassRef | CXCursor_ObjCSuperClassRef => Some("class"),
+        CXCursor_ObjCProtocolDecl | CXCursor_ObjCProtocolRef => Some("protocol"),
+        CXCursor_ObjCMessageExpr | CXCursor_ObjCInstanceMethodDecl | CXCursor_ObjCClassMethodDecl => Some("selector"),
+        CXCursor_ObjCIvarDecl => Some("ivar"),
+        CXCursor_ObjCPropertyDecl => Some("property"),
+        CXCursor_MacroDefinition | CXCursor_MacroExpansion => Some("macro"),
+        CXCursor_EnumConstantDecl => Some("enum-constant"),
+        CXCursor_ParmDecl => Some("parameter"),
+        _ => None
+    }
+}
+
+// Length is counted in the column encoding too
+pub fn highlight(original_file: &Path, input_file: &Path, encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_with_flags_for(original_file, input_file, [CXTranslationUnit_DetailedPreprocessingRecord]));
+    let source = try!(SourceFile::open(input_file));
+
+    let mut output = StrBuf::new();
+    for token in tu.tokens().iter() {
+        match semantic_class(token) {
+            Some(class) => {
+                let (line, column) = (token.location.line, token.location.column);
+                let start = encoded_column(&source, line, column, encoding);
+                let end = encoded_column(&source, line, column + token.len(), encoding);
+                output.push_str(format!("{}:{}\t{}\t{}\n", line, start, end - start, class));
+            },
+            None => ()
+        }
+    }
+    return Ok(output);
+}
+
+// Enclosing expressions, statements, methods and classes from the innermost,
+// each one strictly larger than the previous
+pub fn select_expansion(original_file: &Path, input_file: &Path, range: &str,
+                        encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let source = try!(SourceFile::open(input_file));
+    let (start, end) = try!(parse_range(range));
+    let selection = tu.range_at(input_file, start, end);
+
+    let mut output = StrBuf::new();
+    let mut previous_len = selection.len();
+    for cursor in tu.enclosing_cursors(&selection).iter().rev() {
+        let extent = cursor.extent();
+        if extent.len() <= previous_len { continue; }
+
+        output.push_str(format!("{}:{}-{}:{}\t{}\n", extent.start.line,
+                                encoded_column(&source, extent.start.line, extent.start.column, encoding),
+                                extent.end.line, encoded_column(&source, extent.end.line, extent.end.column, encoding),
+                                cursor.kind_spelling()));
+        previous_len = extent.len();
+    }
     return Ok(output);
 }
 
-pub fn goto_definition(original_file: &Path, input_file: &Path, location: &str) -> Result<~str, ~str> {
-    let cdb = try!(c_db_for(original_file));
+fn format_type_entries(entries: &[TypeEntry], encoding: ColumnEncoding) -> StrBuf {
+    let mut output = StrBuf::new();
+    for entry in entries.iter() {
+        output.push_str(format!("{}\t{}\t{}\t{}\n", entry.relation, entry.depth, entry.name,
+                                encode_location(entry.location.to_str().as_slice(), encoding)));
+    }
+    output
+}
+
+// Superclass chain, adopted protocols and categories of the class or protocol at point
+pub fn supertypes(original_file: &Path, input_file: &Path, location: &str,
+                  encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
     let tu = try!(tu_for(original_file, input_file));
     let (line, column) = try!(parse_location(location));
 
-    let source_location = tu.go_to_definition(&cdb, input_file, line, column);
-    return Ok(source_location.to_str());
+    match type_at(&tu, input_file, line, column) {
+        Some(declaration) => {
+            Ok(format_type_entries(type_hierarchy::supertypes(&tu, &declaration).as_slice(), encoding))
+        },
+        None => Err(~"Unable to find class or protocol at the location")
+    }
+}
+
+// Subclasses and conformers are collected from all files in the compilation database
+pub fn subtypes(original_file: &Path, input_file: &Path, location: &str,
+                encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let declaration = match type_at(&tu, input_file, line, column) {
+        Some(declaration) => declaration,
+        None => return Err(~"Unable to find class or protocol at the location")
+    };
+
+    let config = try!(config_for(original_file));
+    let c_db = try!(c_db_for(original_file));
+    let options = parse_options_for(&config, [CXTranslationUnit_SkipFunctionBodies]);
+
+    let mut relations = TypeRelations::new();
+    relations.collect(&tu);
+    each_project_tu(original_file, &c_db, &config, c_db.files().as_slice(), &options, |_, file_tu| {
+        relations.collect(file_tu);
+        true
+    });
+
+    Ok(format_type_entries(relations.subtypes(declaration.usr()).as_slice(), encoding))
+}
+
+fn format_call_entries(entries: &[CallEntry], encoding: ColumnEncoding) -> StrBuf {
+    let mut output = StrBuf::new();
+    for entry in entries.iter() {
+        output.push_str(format!("{}\t{}\t{}\n", entry.name, encode_range(&entry.call_site, encoding),
+                                encode_location(entry.location.to_str().as_slice(), encoding)));
+    }
+    output
+}
+
+// Callees of the function or method at point with call sites inside its body, definition
+// from another file is looked up in the compilation database
+pub fn outgoing_calls(original_file: &Path, input_file: &Path, location: &str,
+                      encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let callable = match callable_at(&tu, input_file, line, column) {
+        Some(callable) => callable,
+        None => return Err(~"Unable to find function or method at the location")
+    };
+
+    if !callable.definition().is_null() {
+        return Ok(format_call_entries(call_hierarchy::outgoing_calls(&callable).as_slice(), encoding));
+    }
+
+    let usr = callable.usr();
+    let config = try!(config_for(original_file));
+    let c_db = try!(c_db_for(original_file));
+    let mut output = StrBuf::new();
+    each_project_tu(original_file, &c_db, &config, c_db.files().as_slice(), &parse_options_for(&config, []),
+                    |file, file_tu| {
+        match definition_in(file_tu, file, usr.as_slice()) {
+            Some(definition) => {
+                output = format_call_entries(call_hierarchy::outgoing_calls(&definition).as_slice(), encoding);
+                false
+            },
+            None => true
+        }
+    });
+    Ok(output)
+}
+
+// Callers of the function or method at point with call sites from all files in the compilation database
+pub fn incoming_calls(original_file: &Path, input_file: &Path, location: &str,
+                      encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let usr = match callable_at(&tu, input_file, line, column) {
+        Some(callable) => callable.usr(),
+        None => return Err(~"Unable to find function or method at the location")
+    };
+
+    let config = try!(config_for(original_file));
+    let c_db = try!(c_db_for(original_file));
+    let mut entries = call_hierarchy::incoming_calls(&tu, input_file, usr.as_slice());
+    each_project_tu(original_file, &c_db, &config, c_db.files().as_slice(), &parse_options_for(&config, []),
+                    |file, file_tu| {
+        entries.push_all_move(call_hierarchy::incoming_calls(file_tu, file, usr.as_slice()));
+        true
+    });
+    Ok(format_call_entries(entries.as_slice(), encoding))
+}
+
+// Methods overridden by the method at point, including protocol declarations, nearest first
+pub fn goto_super_method(original_file: &Path, input_file: &Path, location: &str,
+                         encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let method = match method_at(&tu, input_file, line, column) {
+        Some(method) => method,
+        None => return Err(~"Unable to find method at the location")
+    };
+
+    let mut output = StrBuf::new();
+    for overridden in super_methods(&method).iter() {
+        output.push_str(format!("{}\t{}\n", call_hierarchy::display_name(overridden),
+                                encode_location(overridden.location().to_str().as_slice(), encoding)));
+    }
+    Ok(output)
+}
+
+// Implementations overriding the method at point in all files of the compilation database
+pub fn find_overrides(original_file: &Path, input_file: &Path, location: &str,
+                      encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let usr = match method_at(&tu, input_file, line, column) {
+        Some(method) => method.usr(),
+        None => return Err(~"Unable to find method at the location")
+    };
+
+    let format_overrides = |methods: Vec<Cursor>| -> ~str {
+        methods.iter().map(|method| {
+            format!("{}\t{}\n", call_hierarchy::display_name(method),
+                    encode_location(method.location().to_str().as_slice(), encoding))
+        }).collect::<Vec<~str>>().concat()
+    };
+
+    let config = try!(config_for(original_file));
+    let c_db = try!(c_db_for(original_file));
+    let mut output = StrBuf::new();
+    output.push_str(format_overrides(overrides_in(&tu, input_file, usr.as_slice())));
+    each_project_tu(original_file, &c_db, &config, c_db.files().as_slice(), &parse_options_for(&config, []),
+                    |file, file_tu| {
+        output.push_str(format_overrides(overrides_in(file_tu, file, usr.as_slice())));
+        true
+    });
+    Ok(output)
+}
+
+// Locations use SDK of the original file compilation command
+fn locator_for(original_file: &Path, spelling: bool) -> Result<Locator, ~str> {
+    let c_db = try!(c_db_for(original_file));
+    let sysroot = c_db.compilation_command_for(original_file).and_then(|c_data| c_data.sysroot());
+    Ok(Locator::new(spelling, sysroot))
+}
+
+pub fn goto_declaration(original_file: &Path, input_file: &Path, location: &str,
+                        spelling: bool) -> Result<Vec<~str>, ~str> {
+    let tu = try!(cached_tu_for(original_file, input_file));
+    let locator = try!(locator_for(original_file, spelling));
+    let (line, column) = try!(parse_location(location));
+
+    let mut declarations = Vec::new();
+    match symbol_at(&tu, input_file, line, column) {
+        Some(symbol) => locator.push_locations(&mut declarations, declarations_of(&tu, &symbol).as_slice()),
+        None => ()
+    }
+    Ok(declarations)
+}
+
+// Definition is looked up in the file, then in the implementation file next to the declaration
+// and other files in the compilation database, symbols without definition (ex: SDK) fallback to declarations
+// in headers
+pub fn goto_definition(original_file: &Path, input_file: &Path, location: &str,
+                       spelling: bool) -> Result<Vec<~str>, ~str> {
+    let tu = try!(cached_tu_for(original_file, input_file));
+    let locator = try!(locator_for(original_file, spelling));
+    let (line, column) = try!(parse_location(location));
+    let symbol = match symbol_at(&tu, input_file, line, column) {
+        Some(symbol) => symbol,
+        None => return Ok(Vec::new())
+    };
+
+    let mut definitions = Vec::new();
+    // Macro is defined where it is declared
+    if symbol.kind() == CXCursor_MacroDefinition {
+        locator.push_locations(&mut definitions, [symbol]);
+        return Ok(definitions);
+    }
+
+    let usr = symbol.usr();
+    let declaration_file = symbol.location().file;
+    locator.push_locations(&mut definitions, definitions_of(&tu, usr.as_slice()).as_slice());
+
+    if definitions.len() == 0 && !declaration_file.contains(".sdk/") {
+        let config = try!(config_for(original_file));
+        let c_db = try!(c_db_for(original_file));
+        let implementation_file = Path::new(declaration_file.as_slice()).with_extension("m");
+        let (preferred, others) = c_db.files().partition(|file| *file == implementation_file);
+        let files: Vec<Path> = preferred.move_iter().chain(others.move_iter()).collect();
+
+        each_project_tu(original_file, &c_db, &config, files.as_slice(), &parse_options_for(&config, []),
+                        |_, file_tu| {
+            locator.push_locations(&mut definitions, definitions_of(file_tu, usr.as_slice()).as_slice());
+            definitions.len() == 0
+        });
+    }
+
+    if definitions.len() == 0 {
+        locator.push_locations(&mut definitions, declarations_of(&tu, &symbol).as_slice());
+    }
+    Ok(definitions)
+}
+
+// Implementations of the method, function or class at point from all files in the compilation database,
+// messages sent to `id` match all methods with the selector
+pub fn goto_implementation(original_file: &Path, input_file: &Path, location: &str,
+                           spelling: bool) -> Result<Vec<~str>, ~str> {
+    let tu = try!(cached_tu_for(original_file, input_file));
+    let locator = try!(locator_for(original_file, spelling));
+    let (line, column) = try!(parse_location(location));
+    let symbol = match symbol_at(&tu, input_file, line, column) {
+        Some(symbol) => symbol,
+        None => return Ok(Vec::new())
+    };
+    let by_selector = message_at(&tu, input_file, line, column).map_or(false, |message| {
+        message.receiver_type_kind() == CXType_ObjCId
+    });
+
+    let config = try!(config_for(original_file));
+    let c_db = try!(c_db_for(original_file));
+    let mut implementations = Vec::new();
+    locator.push_locations(&mut implementations, implementations_in(&tu, input_file, &symbol, by_selector).as_slice());
+    each_project_tu(original_file, &c_db, &config, c_db.files().as_slice(), &parse_options_for(&config, []),
+                    |file, file_tu| {
+        let found = implementations_in(file_tu, file, &symbol, by_selector);
+        locator.push_locations(&mut implementations, found.as_slice());
+        true
+    });
+    Ok(implementations)
+}
+
+static PROPERTY_ATTRIBUTES: &'static [(c_uint, &'static str)] = &[
+    (CXObjCPropertyAttr_readonly, "readonly"), (CXObjCPropertyAttr_readwrite, "readwrite"),
+    (CXObjCPropertyAttr_getter, "getter"), (CXObjCPropertyAttr_setter, "setter"),
+    (CXObjCPropertyAttr_assign, "assign"), (CXObjCPropertyAttr_retain, "retain"),
+    (CXObjCPropertyAttr_copy, "copy"), (CXObjCPropertyAttr_weak, "weak"),
+    (CXObjCPropertyAttr_strong, "strong"), (CXObjCPropertyAttr_unsafe_unretained, "unsafe_unretained"),
+    (CXObjCPropertyAttr_atomic, "atomic"), (CXObjCPropertyAttr_nonatomic, "nonatomic")
+];
+
+static DECL_QUALIFIERS: &'static [(c_uint, &'static str)] = &[
+    (CXObjCDeclQualifier_In, "in"), (CXObjCDeclQualifier_Inout, "inout"), (CXObjCDeclQualifier_Out, "out"),
+    (CXObjCDeclQualifier_Bycopy, "bycopy"), (CXObjCDeclQualifier_Byref, "byref"),
+    (CXObjCDeclQualifier_Oneway, "oneway")
+];
+
+fn flag_names(flags: c_uint, names: &[(c_uint, &'static str)]) -> ~str {
+    let mut set = Vec::new();
+    for &(flag, name) in names.iter() {
+        if flags & flag != 0 { set.push(name); }
+    }
+    set.connect(",")
+}
+
+// Attributes of the declaration at point or referenced at point, one `key\tvalue` per line
+pub fn describe(original_file: &Path, input_file: &Path, location: &str,
+                encoding: ColumnEncoding) -> Result<StrBuf, ~str> {
+    let tu = try!(tu_for(original_file, input_file));
+    let (line, column) = try!(parse_location(location));
+    let cursor = tu.cursor_at(input_file, line, column);
+    let entity = if cursor.referenced().is_null() { cursor } else { cursor.referenced() };
+    if entity.is_null() { return Err(~"Unable to find declaration at the location"); }
+
+    let mut output = StrBuf::new();
+    output.push_str(format!("kind\t{}\nname\t{}\ntype\t{}\nlocation\t{}\n", entity.kind_spelling(),
+                            entity.spelling(), entity.type_spelling(),
+                            encode_location(entity.location().to_str().as_slice(), encoding)));
+
+    match entity.kind() {
+        CXCursor_ObjCPropertyDecl => {
+            output.push_str(format!("attributes\t{}\ngetter\t{}\nsetter\t{}\n",
+                                    flag_names(entity.property_attributes(), PROPERTY_ATTRIBUTES),
+                                    entity.property_getter_name(), entity.property_setter_name()));
+            output.push_str(format!("optional\t{}\n", entity.is_optional()));
+        },
+        CXCursor_ObjCInstanceMethodDecl | CXCursor_ObjCClassMethodDecl => {
+            output.push_str(format!("qualifiers\t{}\n", flag_names(entity.decl_qualifiers(), DECL_QUALIFIERS)));
+            output.push_str(format!("optional\t{}\nvariadic\t{}\n", entity.is_optional(), entity.is_variadic()));
+        },
+        CXCursor_ParmDecl => {
+            output.push_str(format!("qualifiers\t{}\n", flag_names(entity.decl_qualifiers(), DECL_QUALIFIERS)));
+        },
+        CXCursor_FunctionDecl => output.push_str(format!("variadic\t{}\n", entity.is_variadic())),
+        _ => ()
+    }
+
+    match cursor.selector_index() {
+        Some(index) => output.push_str(format!("selector-index\t{}\n", index)),
+        None => ()
+    }
+    Ok(output)
+}
+
+// Converts location or range to byte based line:column, single number is a byte offset
+pub fn normalize_location(file_path: &Path, location: &str, encoding: ColumnEncoding) -> Result<~str, ~str> {
+    let source = try!(SourceFile::open(file_path));
+    let mut normalized = Vec::new();
+    for part in location.split('-') {
+        let (line, column) = match from_str::<uint>(part) {
+            Some(offset) => match source.location_for(offset) {
+                Some(location) => location,
+                None => return Err(format!("Offset is in the middle of a character: {}", offset))
+            },
+            None => {
+                let (line, column) = try!(parse_location(part));
+                match source.byte_column(line, column, encoding) {
+                    Some(byte_column) => (line, byte_column),
+                    None => return Err(format!("Location is outside of the file: {}", part))
+                }
+            }
+        };
+        normalized.push(line.to_str() + ":" + column.to_str());
+    }
+    Ok(normalized.connect("-"))
+}
+
+// Converts column of file:line:column returned by libclang to the encoding
+pub fn encode_location(location: &str, encoding: ColumnEncoding) -> ~str {
+    let split: Vec<&str> = location.rsplitn(':', 2).collect();
+    if split.len() != 3 { return location.to_owned(); }
+
+    let (file, line, column) = (*split.get(2), from_str::<uint>(*split.get(1)), from_str::<uint>(*split.get(0)));
+    let encoded = match (line, column, SourceFile::open(&Path::new(file))) {
+        (Some(line), Some(column), Ok(source)) => source.encoded_column(line, column, encoding).map(|c| (line, c)),
+        _ => None
+    };
+
+    match encoded {
+        Some((line, column)) => format!("{}:{}:{}", file, line, column),
+        None => location.to_owned()
+    }
+}
+
+// libclang byte column is kept when it can't be converted
+fn encoded_column(source: &SourceFile, line: uint, column: uint, encoding: ColumnEncoding) -> uint {
+    source.encoded_column(line, column, encoding).unwrap_or(column)
+}
+
+// Range is printed as file:line:column-line:column
+fn encode_range(range: &SourceRange, encoding: ColumnEncoding) -> ~str {
+    let end_column = match SourceFile::open(&Path::new(range.end.file.as_slice())) {
+        Ok(source) => encoded_column(&source, range.end.line, range.end.column, encoding),
+        Err(_) => range.end.column
+    };
+    format!("{}-{}:{}", encode_location(range.start.to_str().as_slice(), encoding), range.end.line, end_column)
+}
+
+// Range is in format line:column-line:column, single location is an empty range
+fn parse_range(range: &str) -> Result<((uint, uint), (uint, uint)), ~str> {
+    let range_split: Vec<&str> = range.split('-').collect();
+    match range_split.len() {
+        1 => {
+            let location = try!(parse_location(range));
+            Ok((location, location))
+        },
+        2 => Ok((try!(parse_location(*range_split.get(0))), try!(parse_location(*range_split.get(1))))),
+        _ => Err(~"Range should be in format line:column-line:column")
+    }
 }
 
 fn parse_location(location: &str) -> Result<(uint, uint), ~str> {
     let loc_split: ~[&str] = location.split_str(":").collect();
     if loc_split.len() != 2 { return Err(~"Location should be in format line:column") }
 
-    let line:uint = from_str(loc_split[0]).unwrap();
-    let column:uint = from_str(loc_split[1]).unwrap();
-    Ok((line, column))
+    match (from_str::<uint>(loc_split[0]), from_str::<uint>(loc_split[1])) {
+        (Some(line), Some(column)) => Ok((line, column)),
+        _ => Err(~"Location should be in format line:column")
+    }
 }
 
 fn c_db_for(file_path: &Path) -> Result<CompilationDatabase, ~str> {
@@ -69,17 +793,86 @@ fn c_db_for(file_path: &Path) -> Result<CompilationDatabase, ~str> {
         return Err(~"Unable to find directory with compile_commands.json");
     }
 
+    let config = try!(config_for(file_path));
     match CompilationDatabase::from_directory(&path) {
-        Ok(c_db) => Ok(c_db),
+        Ok(c_db) => {
+            let mut c_db = c_db;
+            c_db.set_argument_rewriter(config.argument_rewriter());
+            Ok(c_db)
+        },
         Err(_e) => Err(~"Unable to create compilation database")
     }
 }
 
+fn config_for(file_path: &Path) -> Result<Config, ~str> {
+    match try!(Config::find(file_path)) {
+        Some(config) => Ok(config),
+        None => Ok(Config::new(file_path.dir_path()))
+    }
+}
+
+fn parse_options_for(config: &Config, flags: &[CXTranslationUnit_Flags]) -> ParseOptions {
+    config.parse_flags.iter().chain(flags.iter()).fold(ParseOptions::editing(), |options, &flag| {
+        options.with(flag)
+    })
+}
+
+// Other project files have no unsaved changes, so their saved AST is reused
+fn project_tu_for(c_db: &CompilationDatabase, file_path: &Path, options: &ParseOptions) -> Option<TranslationUnit> {
+    c_db.compilation_command_for(file_path).map(|c_data| {
+        let cache = AstCache::default();
+        match cache.load(file_path, &c_data, options) {
+            Some(tu) => tu,
+            None => {
+                let tu = TranslationUnit::with_options(&c_data, file_path, options);
+                match cache.store(&tu, file_path, &c_data, options) {
+                    Ok(_) => (),
+                    Err(e) => debug!("{}", e)
+                }
+                tu
+            }
+        }
+    })
+}
+
+// Visits translation units of the files except the original and excluded ones,
+// stops once `f` returns false
+fn each_project_tu(original_file: &Path, c_db: &CompilationDatabase, config: &Config, files: &[Path],
+                   options: &ParseOptions, f: |&Path, &TranslationUnit| -> bool) {
+    for file in files.iter() {
+        if file == original_file || config.is_excluded(file) { continue; }
+        match project_tu_for(c_db, file, options) {
+            Some(file_tu) => if !f(file, &file_tu) { break; },
+            None => ()
+        }
+    }
+}
+
 fn tu_for(original_file: &Path, input_file: &Path) -> Result<TranslationUnit, ~str> {
+    tu_with_flags_for(original_file, input_file, [])
+}
+
+fn tu_with_flags_for(original_file: &Path, input_file: &Path,
+                     flags: &[CXTranslationUnit_Flags]) -> Result<TranslationUnit, ~str> {
+    let config = try!(config_for(original_file));
     let c_db = try!(c_db_for(original_file));
 
     match c_db.compilation_command_for(original_file) {
-        Some(c_data) => Ok(TranslationUnit::new(&c_data, input_file)),
+        Some(c_data) => Ok(TranslationUnit::with_options(&c_data, input_file, &parse_options_for(&config, flags))),
+        None => Err(~"Unable to find compilation command in the database")
+    }
+}
+
+// Saved AST is reused only for files without unsaved changes, it can't be used for completion.
+// Detailed preprocessing record keeps macro expansions, so navigation works for macros
+fn cached_tu_for(original_file: &Path, input_file: &Path) -> Result<TranslationUnit, ~str> {
+    let flags = [CXTranslationUnit_DetailedPreprocessingRecord];
+    if original_file != input_file { return tu_with_flags_for(original_file, input_file, flags); }
+
+    let config = try!(config_for(original_file));
+    let c_db = try!(c_db_for(original_file));
+    match project_tu_for(&c_db, original_file, &parse_options_for(&config, flags)) {
+        Some(tu) => Ok(tu),
         None => Err(~"Unable to find compilation command in the database")
     }
 }
@@ -88,7 +881,13 @@ fn tu_for(original_file: &Path, input_file: &Path) -> Result<TranslationUnit, ~s
 mod test {
 
     use std::os;
-    use super::{syntax_check, code_completion, goto_definition};
+    use std::io::{File, TempDir};
+    use super::{syntax_check, code_completion, completion_context, signature_help, stats, highlight,
+                select_expansion, supertypes, subtypes, outgoing_calls, incoming_calls, goto_super_method,
+                find_overrides, goto_declaration, goto_definition, goto_implementation, describe,
+                normalize_location, format_diagnostics, tu_for, CompletionOptions};
+    use rclang::source_file::{ByteColumn, CharColumn};
+    use config::{Config, ShowWarnings, HideWarnings, WarningsAsErrors, JsonOutput};
 
     #[test]
     fn test_syntax_check() {
@@ -98,20 +897,246 @@ mod test {
         assert!(diagnostic.unwrap() == StrBuf::from_str(input.as_str().unwrap() + ":17:15: warning: unused variable 'testString' [-Wunused-variable]\n"));
     }
 
+    #[test]
+    fn test_warning_policy() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let diagnostics = tu_for(&input, &input).unwrap().diagnostics();
+        let mut config = Config::new(input.dir_path());
+
+        config.warnings = HideWarnings;
+        assert!(format_diagnostics(diagnostics.as_slice(), &config).len() == 0);
+
+        config.warnings = WarningsAsErrors;
+        assert!(format_diagnostics(diagnostics.as_slice(), &config) == StrBuf::from_str(input.as_str().unwrap() + ":17:15: error: unused variable 'testString' [-Wunused-variable]\n"));
+
+        config.format = JsonOutput;
+        let json = format_diagnostics(diagnostics.as_slice(), &config);
+        assert!(json.as_slice().contains("\"line\":17,\"column\":15,\"severity\":\"error\""));
+
+        config.warnings = ShowWarnings;
+        let json = format_diagnostics(diagnostics.as_slice(), &config);
+        assert!(json.as_slice().contains("\"severity\":\"warning\",\"message\":\"unused variable 'testString'\""));
+    }
+
     #[test]
     fn test_code_completion() {
         let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
-        let completion = code_completion(&input, &input, "16:18", "pre");
+        let directory = TempDir::new("xclang-completion-cache").unwrap();
+        let mut options = CompletionOptions::new(~"pre");
+        options.cache_directory = Some(directory.path().clone());
+        let completion = code_completion(&input, &input, "16:18", &options);
         assert!(completion.is_ok());
         assert!(completion.unwrap() == StrBuf::from_str("prepareToTest\tvoid\n"));
     }
 
+    #[test]
+    fn test_code_completion_fuzzy_limit() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let mut options = CompletionOptions::new(~"ptt");
+        options.cache = false;
+        let completion = code_completion(&input, &input, "16:18", &options);
+        assert!(completion.is_ok());
+        assert!(completion.unwrap().as_slice().starts_with("prepareToTest\tvoid\n"));
+
+        options.limit = Some(0);
+        assert!(code_completion(&input, &input, "16:18", &options).unwrap().len() == 0);
+    }
+
+    #[test]
+    fn test_completion_context() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let context = completion_context(&input, &input, "16:16");
+        assert!(context.is_ok());
+        assert!(context.unwrap().as_slice().contains("objc-class-message"));
+    }
+
+    #[test]
+    fn test_signature_help() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let signatures = signature_help(&input, &input, "18:51");
+        assert!(signatures.is_ok());
+        let signatures = signatures.unwrap();
+        assert!(signatures.as_slice().lines().any(|line| line.contains("initWithFrame:") && line.contains("\t0")));
+    }
+
+    #[test]
+    fn test_stats() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let stats = stats(&input, &input, []);
+        assert!(stats.is_ok());
+        let stats = stats.unwrap();
+        assert!(stats.as_slice().starts_with("parse-time\t"));
+        assert!(stats.as_slice().contains("\ntotal\t"));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let highlight = highlight(&input, &input, ByteColumn);
+        assert!(highlight.is_ok());
+        let highlight = highlight.unwrap();
+        assert!(highlight.as_slice().contains("16:6\t9\tclass\n"));
+        assert!(highlight.as_slice().contains("16:16\t13\tselector\n"));
+        assert!(highlight.as_slice().contains("18:10\t6\tproperty\n"));
+    }
+
+    #[test]
+    fn test_select_expansion() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let expansion = select_expansion(&input, &input, "16:16-16:29", ByteColumn);
+        assert!(expansion.is_ok());
+        let expansion = expansion.unwrap();
+        let mut lines = expansion.as_slice().lines();
+        assert!(lines.next() == Some("16:5-16:30\tObjCMessageExpr"));
+        assert!(lines.last().unwrap().ends_with("\tObjCImplementationDecl"));
+    }
+
+    #[test]
+    fn test_supertypes() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let supertypes = supertypes(&input, &input, "12:20", ByteColumn);
+        assert!(supertypes.is_ok());
+        let supertypes = supertypes.unwrap();
+        assert!(supertypes.as_slice().starts_with("superclass\t1\tUIResponder\t"));
+        assert!(supertypes.as_slice().contains("superclass\t2\tNSObject\t"));
+        assert!(supertypes.as_slice().contains("protocol\t1\tUIApplicationDelegate\t"));
+    }
+
+    #[test]
+    fn test_subtypes() {
+        let original = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let header = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.h");
+        let subtypes = subtypes(&original, &header, "11:27", ByteColumn);
+        assert!(subtypes.is_ok());
+        assert!(subtypes.unwrap().as_slice().contains("subclass\t1\tAppDelegate\t"));
+
+        let subtypes = subtypes(&original, &original, "12:20", ByteColumn);
+        assert!(subtypes.is_ok());
+        assert!(subtypes.unwrap().len() == 0);
+    }
+
+    #[test]
+    fn test_outgoing_calls() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let calls = outgoing_calls(&input, &input, "16:6", ByteColumn);
+        assert!(calls.is_ok());
+        let calls = calls.unwrap();
+        assert!(calls.as_slice().starts_with("+[TestClass prepareToTest]\t" + input.as_str().unwrap() + ":16:5-16:30\t"));
+        assert!(calls.as_slice().contains("-[UIWindow makeKeyAndVisible]\t"));
+    }
+
+    #[test]
+    fn test_incoming_calls() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/TestClass.m");
+        let caller = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let calls = incoming_calls(&input, &input, "13:12", ByteColumn);
+        assert!(calls.is_ok());
+        let calls = calls.unwrap();
+        assert!(calls.as_slice().starts_with("-[AppDelegate application:didFinishLaunchingWithOptions:]\t" +
+                                             caller.as_str().unwrap() + ":16:5-16:30\t"));
+    }
+
+    #[test]
+    fn test_goto_super_method() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let methods = goto_super_method(&input, &input, "14:20", ByteColumn);
+        assert!(methods.is_ok());
+        let methods = methods.unwrap();
+        assert!(methods.as_slice().starts_with("-[UIApplicationDelegate application:didFinishLaunchingWithOptions:]\t"));
+        assert!(methods.as_slice().contains("UIApplication.h:"));
+    }
+
+    #[test]
+    fn test_find_overrides() {
+        let original = os::getcwd().join("tests/TestApplication/TestApplication/TestClass.m");
+        let overrides = find_overrides(&original, &original, "13:12", ByteColumn);
+        assert!(overrides.is_ok());
+        assert!(overrides.unwrap().len() == 0);
+
+        // Subclass is parsed with the compilation command of the original file
+        let directory = TempDir::new("xclang-overrides").unwrap();
+        let input = directory.path().join("TestClass.m");
+        let header = original.with_filename("TestClass.h");
+        let contents = File::open(&original).read_to_str().unwrap()
+            .replace("#import \"TestClass.h\"", format!("#import \"{}\"", header.display()).as_slice())
+            + "\n@interface TestSubclass : TestClass\n@end\n\n@implementation TestSubclass\n\n"
+            + "+ (void)prepareToTest\n{\n}\n\n@end\n";
+        assert!(File::create(&input).write_str(contents.as_slice()).is_ok());
+
+        let overrides = find_overrides(&original, &input, "13:12", ByteColumn).unwrap();
+        assert!(overrides.as_slice().starts_with("+[TestSubclass prepareToTest]\t"));
+        assert!(overrides.as_slice().contains(format!("{}:24:", input.display()).as_slice()));
+    }
+
+    #[test]
+    fn test_describe() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let header = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.h");
+        let description = describe(&input, &header, "13:41", ByteColumn);
+        assert!(description.is_ok());
+        let description = description.unwrap();
+        assert!(description.as_slice().starts_with("kind\tObjCPropertyDecl\nname\twindow\ntype\tUIWindow *\n"));
+        assert!(description.as_slice().lines().any(|line| {
+            line.starts_with("attributes\t") && line.contains("strong") && line.contains("nonatomic") && !line.contains("copy")
+        }));
+        assert!(description.as_slice().contains("\nsetter\tsetWindow:\n"));
+
+        let description = describe(&input, &input, "18:37", ByteColumn).unwrap();
+        assert!(description.as_slice().contains("\nname\tinitWithFrame:\n"));
+        assert!(description.as_slice().contains("\nselector-index\t0\n"));
+    }
+
+    #[test]
+    fn test_normalize_location() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        assert!(normalize_location(&input, "16:16", CharColumn) == Ok(~"16:16"));
+        assert!(normalize_location(&input, "16:16-16:29", ByteColumn) == Ok(~"16:16-16:29"));
+        assert!(normalize_location(&input, "0", ByteColumn) == Ok(~"1:1"));
+        assert!(normalize_location(&input, "16:1000", CharColumn).is_err());
+    }
+
     #[test]
     fn test_goto_definition() {
         let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
         let def_file = os::getcwd().join("tests/TestApplication/TestApplication/TestClass.m");
-        let location = goto_definition(&input, &input, "16:18");
-        assert!(location.is_ok());
-        assert!(location.unwrap() == (def_file.as_str().unwrap() + ":13:9"));
+        let locations = goto_definition(&input, &input, "16:18", false);
+        assert!(locations.is_ok());
+        assert!(locations.unwrap() == vec!(def_file.as_str().unwrap() + ":13:9"));
+    }
+
+    #[test]
+    fn test_goto_definition_through_macro() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let locations = goto_definition(&input, &input, "22:12", false);
+        assert!(locations.is_ok());
+        let locations = locations.unwrap();
+        assert!(locations.len() == 1);
+        assert!(locations.get(0).contains("objc.h:"));
+    }
+
+    #[test]
+    fn test_goto_definition_in_sdk() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let locations = goto_definition(&input, &input, "18:37", false);
+        assert!(locations.is_ok());
+        assert!(locations.unwrap().iter().any(|location| location.contains("UIView.h:")));
+    }
+
+    #[test]
+    fn test_goto_declaration() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let decl_file = os::getcwd().join("tests/TestApplication/TestApplication/TestClass.h");
+        let locations = goto_declaration(&input, &input, "16:18", false);
+        assert!(locations.is_ok());
+        assert!(locations.unwrap() == vec!(decl_file.as_str().unwrap() + ":13:9"));
+    }
+
+    #[test]
+    fn test_goto_implementation() {
+        let input = os::getcwd().join("tests/TestApplication/TestApplication/AppDelegate.m");
+        let impl_file = os::getcwd().join("tests/TestApplication/TestApplication/TestClass.m");
+        let locations = goto_implementation(&input, &input, "16:18", false);
+        assert!(locations.is_ok());
+        assert!(locations.unwrap() == vec!(impl_file.as_str().unwrap() + ":13:9"));
     }
 }
@@ -0,0 +1,175 @@
+use libc::c_uint;
+use std::os;
+
+use rclang::cursor::Cursor;
+use rclang::translation_unit::TranslationUnit;
+use rclang::types::*;
+
+use call_hierarchy::{definitions_in, is_callable};
+use overrides::super_methods;
+use type_hierarchy::extended_class;
+
+/**
+ * Navigation
+ **/
+
+fn is_container(kind: c_uint) -> bool {
+    match kind {
+        CXCursor_ObjCInterfaceDecl | CXCursor_ObjCCategoryDecl | CXCursor_ObjCProtocolDecl |
+        CXCursor_ObjCImplementationDecl | CXCursor_ObjCCategoryImplDecl => true,
+        _ => false
+    }
+}
+
+fn is_implementation(cursor: &Cursor) -> bool {
+    match cursor.kind() {
+        CXCursor_ObjCImplementationDecl | CXCursor_ObjCCategoryImplDecl => true,
+        kind => is_callable(kind) && cursor.is_definition()
+    }
+}
+
+// Declaration referenced or declared at point, macro definition for macro expansions
+pub fn symbol_at(tu: &TranslationUnit, file_path: &Path, line: uint, column: uint) -> Option<Cursor> {
+    let cursor = tu.cursor_at(file_path, line, column);
+    if cursor.kind() == CXCursor_MacroExpansion && !cursor.referenced().is_null() {
+        return Some(cursor.referenced());
+    }
+
+    let range = tu.range_at(file_path, (line, column), (line, column));
+    match tu.innermost_cursor(&range) {
+        Some(cursor) if !cursor.referenced().is_null() => Some(cursor.referenced()),
+        _ => None
+    }
+}
+
+// Message sent at point, receiver type decides how implementations are matched
+pub fn message_at(tu: &TranslationUnit, file_path: &Path, line: uint, column: uint) -> Option<Cursor> {
+    let range = tu.range_at(file_path, (line, column), (line, column));
+    match tu.innermost_cursor(&range) {
+        Some(cursor) if cursor.kind() == CXCursor_ObjCMessageExpr => Some(cursor),
+        _ => None
+    }
+}
+
+// Top level declarations and members of Objective-C containers with the USR
+fn cursors_with(tu: &TranslationUnit, usr: &str) -> Vec<Cursor> {
+    let mut cursors = Vec::new();
+    for cursor in tu.cursor().children().iter() {
+        if cursor.usr().as_slice() == usr { cursors.push(*cursor); }
+        if !is_container(cursor.kind()) { continue; }
+
+        for child in cursor.children().iter() {
+            if child.usr().as_slice() == usr { cursors.push(*child); }
+        }
+    }
+    cursors
+}
+
+// Interfaces, protocols and method or function prototypes, the symbol itself if nothing else is found
+pub fn declarations_of(tu: &TranslationUnit, symbol: &Cursor) -> Vec<Cursor> {
+    let usr = symbol.usr();
+    let declarations: Vec<Cursor> = cursors_with(tu, usr.as_slice()).move_iter()
+        .filter(|cursor| !is_implementation(cursor)).collect();
+    if declarations.len() > 0 { declarations } else { vec!(*symbol) }
+}
+
+// Function and method bodies, interfaces are definitions of classes
+pub fn definitions_of(tu: &TranslationUnit, usr: &str) -> Vec<Cursor> {
+    cursors_with(tu, usr).move_iter().filter(|cursor| cursor.is_definition()).collect()
+}
+
+// Implementations in the main file of the translation unit: method definitions and overrides,
+// or all methods with the same selector when the message is sent to `id`, class implementations
+// with categories for classes
+pub fn implementations_in(tu: &TranslationUnit, file_path: &Path, symbol: &Cursor, by_selector: bool) -> Vec<Cursor> {
+    let usr = symbol.usr();
+    if is_callable(symbol.kind()) {
+        return definitions_in(tu, file_path).move_iter().filter(|definition| {
+            definition.usr() == usr ||
+                (by_selector && definition.kind() == symbol.kind() && definition.spelling() == symbol.spelling()) ||
+                super_methods(definition).iter().any(|method| method.usr() == usr)
+        }).collect();
+    }
+
+    let file_name = file_path.as_str().unwrap_or("");
+    tu.cursor().children().move_iter().filter(|cursor| {
+        if cursor.location().file.as_slice() != file_name { return false; }
+        match cursor.kind() {
+            CXCursor_ObjCImplementationDecl => cursor.usr() == usr,
+            CXCursor_ObjCCategoryImplDecl => extended_class(cursor).map_or(false, |class| class.usr() == usr),
+            _ => false
+        }
+    }).collect()
+}
+
+/**
+ * Locator
+ **/
+
+// Reports locations of navigation targets
+pub struct Locator {
+    // Spelling location points into macro definition for declarations produced by macros,
+    // expansion location points to the macro usage
+    pub spelling: bool,
+    pub sysroot:  Option<~str>
+}
+
+impl Locator {
+    pub fn new(spelling: bool, sysroot: Option<~str>) -> Locator {
+        Locator { spelling: spelling, sysroot: sysroot }
+    }
+
+    pub fn location(&self, cursor: &Cursor) -> ~str {
+        let mut location = if self.spelling { cursor.spelling_location() } else { cursor.location() };
+        location.file = self.resolve_sdk_header(location.file);
+        location.to_str()
+    }
+
+    // Headers are included into many translation units, locations are reported once
+    pub fn push_locations(&self, locations: &mut Vec<~str>, cursors: &[Cursor]) {
+        for cursor in cursors.iter() {
+            let location = self.location(cursor);
+            if !locations.contains(&location) { locations.push(location); }
+        }
+    }
+
+    // SDK headers can be reported with the SDK path of the machine which built AST or
+    // precompiled modules, missing headers are looked up in the sysroot of the compilation command
+    pub fn resolve_sdk_header(&self, file: ~str) -> ~str {
+        if file.len() == 0 || Path::new(file.as_slice()).exists() { return file; }
+
+        let (sysroot, sdk_end) = match (self.sysroot.as_ref(), file.find_str(".sdk/")) {
+            (Some(sysroot), Some(idx)) => (sysroot, idx + ".sdk/".len()),
+            _ => return file
+        };
+
+        let header = os::make_absolute(&Path::new(sysroot.as_slice())).join(file.slice_from(sdk_end));
+        match header.as_str() {
+            Some(header_file) if header.exists() => header_file.to_owned(),
+            _ => file
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::os;
+    use navigation::Locator;
+
+    #[test]
+    fn resolve_sdk_header() {
+        let sysroot = os::getcwd().join("tests/TestApplication");
+        let locator = Locator::new(false, Some(sysroot.as_str().unwrap().to_owned()));
+
+        let missing = ~"/Old/SDKs/iPhoneSimulator7.1.sdk/TestApplication/TestClass.h";
+        let header = sysroot.join("TestApplication/TestClass.h");
+        assert!(locator.resolve_sdk_header(missing) == header.as_str().unwrap().to_owned());
+
+        let unknown = ~"/Old/SDKs/iPhoneSimulator7.1.sdk/UIKit/UIView.h";
+        assert!(locator.resolve_sdk_header(unknown.clone()) == unknown);
+
+        let existing = header.as_str().unwrap().to_owned();
+        assert!(locator.resolve_sdk_header(existing.clone()) == existing);
+    }
+}
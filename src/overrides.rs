@@ -0,0 +1,51 @@
+use libc::c_uint;
+
+use rclang::cursor::Cursor;
+use rclang::translation_unit::TranslationUnit;
+use rclang::types::*;
+
+use call_hierarchy::{definitions_in, is_callable};
+
+/**
+ * Method Overrides
+ **/
+
+fn is_method(kind: c_uint) -> bool {
+    kind == CXCursor_ObjCInstanceMethodDecl || kind == CXCursor_ObjCClassMethodDecl
+}
+
+// Method declared or called at point, or the one containing point
+pub fn method_at(tu: &TranslationUnit, file_path: &Path, line: uint, column: uint) -> Option<Cursor> {
+    let range = tu.range_at(file_path, (line, column), (line, column));
+    let cursors = tu.enclosing_cursors(&range);
+
+    match cursors.last() {
+        Some(cursor) if cursor.kind() == CXCursor_ObjCMessageExpr => {
+            let referenced = cursor.referenced();
+            if is_method(referenced.kind()) { return Some(referenced); }
+        },
+        _ => ()
+    }
+    cursors.iter().rev().find(|cursor| is_method(cursor.kind())).map(|cursor| *cursor)
+}
+
+// All methods overridden by the method, nearest first
+pub fn super_methods(method: &Cursor) -> Vec<Cursor> {
+    let mut methods: Vec<Cursor> = Vec::new();
+    let mut queue = method.overridden();
+    while queue.len() > 0 {
+        let overridden = queue.shift().unwrap();
+        if methods.iter().any(|m| m.usr() == overridden.usr()) { continue; }
+        queue.push_all_move(overridden.overridden());
+        methods.push(overridden);
+    }
+    methods
+}
+
+// Methods defined in the main file of the translation unit overriding method with USR
+pub fn overrides_in(tu: &TranslationUnit, file_path: &Path, usr: &str) -> Vec<Cursor> {
+    definitions_in(tu, file_path).move_iter().filter(|definition| {
+        is_callable(definition.kind()) && definition.usr().as_slice() != usr &&
+            super_methods(definition).iter().any(|method| method.usr().as_slice() == usr)
+    }).collect()
+}
@@ -0,0 +1,118 @@
+/**
+ * Argument Rules
+ **/
+
+pub enum ArgumentRule {
+    RemoveFlag(~str),
+    RemoveFlagWithValue(~str),
+    RemoveInputFile,
+    AddFlag(~str),
+    RemapPathPrefix(~str, ~str)
+}
+
+// Flags with path glued to them, ex: -I/usr/include
+static PATH_FLAGS: &'static [&'static str] = &["-I", "-F", "-iquote", "-isystem", "-isysroot", "--sysroot="];
+
+// Prefix matches whole path components only, so `/Applications/Xcode.app`
+// doesn't match `/Applications/Xcode.app-beta`
+fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    if !path.starts_with(prefix) { return None; }
+
+    let rest = path.slice_from(prefix.len());
+    if rest.len() == 0 || rest.starts_with("/") || prefix.ends_with("/") { Some(rest) } else { None }
+}
+
+fn remap_path_prefix(arg: ~str, from: &str, to: &str) -> ~str {
+    match strip_path_prefix(arg.as_slice(), from) {
+        Some(rest) => return format!("{}{}", to, rest),
+        None => ()
+    }
+
+    for flag in PATH_FLAGS.iter() {
+        if !arg.starts_with(*flag) { continue; }
+        match strip_path_prefix(arg.slice_from(flag.len()), from) {
+            Some(rest) => return format!("{}{}{}", *flag, to, rest),
+            None => ()
+        }
+    }
+    arg
+}
+
+impl ArgumentRule {
+    fn apply(&self, file_path: &Path, cwd: &Path, args: Vec<~str>) -> Vec<~str> {
+        match *self {
+            RemoveFlag(ref flag) => args.move_iter().filter(|arg| arg != flag).collect(),
+            RemoveFlagWithValue(ref flag) => {
+                let mut result = Vec::new();
+                let mut skip_value = false;
+                for arg in args.move_iter() {
+                    if skip_value { skip_value = false; continue; }
+                    if arg == *flag { skip_value = true; continue; }
+                    if arg.starts_with(flag.as_slice() + "=") { continue; }
+                    result.push(arg);
+                }
+                result
+            },
+            RemoveInputFile => args.move_iter().filter(|arg| {
+                cwd.join(arg.as_slice()) != *file_path
+            }).collect(),
+            AddFlag(ref flag) => {
+                let mut result = args;
+                result.push(flag.clone());
+                result
+            },
+            RemapPathPrefix(ref from, ref to) => args.move_iter().map(|arg| {
+                remap_path_prefix(arg, from.as_slice(), to.as_slice())
+            }).collect()
+        }
+    }
+}
+
+/**
+ * Argument Rewriter
+ **/
+
+pub struct ArgumentRewriter {
+    rules: Vec<(Option<Path>, ArgumentRule)>
+}
+
+impl ArgumentRewriter {
+    pub fn new() -> ArgumentRewriter {
+        ArgumentRewriter { rules: Vec::new() }
+    }
+
+    // Drops input, output and dependency tracking flags from the compiler
+    // invocation together with flags unknown to libclang
+    pub fn with_default_rules() -> ArgumentRewriter {
+        let mut rewriter = ArgumentRewriter::new();
+        rewriter.add_rule(RemoveInputFile);
+        for flag in ["-c", "-MMD", "-MD", "-fembed-bitcode-marker"].iter() {
+            rewriter.add_rule(RemoveFlag(flag.to_owned()));
+        }
+        for flag in ["-o", "-MF", "-MT", "-MQ", "--serialize-diagnostics", "-index-store-path"].iter() {
+            rewriter.add_rule(RemoveFlagWithValue(flag.to_owned()));
+        }
+        rewriter
+    }
+
+    pub fn add_rule(&mut self, rule: ArgumentRule) {
+        self.rules.push((None, rule));
+    }
+
+    // Rule is applied only to the files inside scope directory
+    pub fn add_scoped_rule(&mut self, scope: Path, rule: ArgumentRule) {
+        self.rules.push((Some(scope), rule));
+    }
+
+    pub fn rewrite(&self, file_path: &Path, cwd: &Path, args: Vec<~str>) -> Vec<~str> {
+        let mut result = args;
+        for &(ref scope, ref rule) in self.rules.iter() {
+            let in_scope = match *scope {
+                Some(ref dir) => dir.is_ancestor_of(file_path),
+                None => true
+            };
+            if in_scope { result = rule.apply(file_path, cwd, result); }
+        }
+        result
+    }
+}
@@ -1,5 +1,6 @@
-use std::fmt;
+use std::{fmt, slice};
 use collections::enum_set;
+use libc::c_uint;
 
 use types::*;
 use ffi::*;
@@ -9,8 +10,33 @@ use ffi::*;
  **/
 
 pub struct CompletionChunk {
-    pub kind: CXCompletionChunkKind,
-    pub text: ~str
+    pub kind:     CXCompletionChunkKind,
+    pub text:     ~str,
+    pub optional: Vec<CompletionChunk>
+}
+
+impl CompletionChunk {
+    pub fn chunks_for(completion: &CXCompletionString) -> Vec<CompletionChunk> {
+        let mut chunks = Vec::new();
+        let num_chunks = unsafe { clang_getNumCompletionChunks(*completion) };
+        for idx in range(0, num_chunks) {
+            let kind = unsafe { clang_getCompletionChunkKind(*completion, idx) };
+            let text = unsafe { clang_getCompletionChunkText(*completion, idx) };
+            let kind: CXCompletionChunkKind = enum_set::CLike::from_uint(kind as uint);
+
+            // Optional chunks carry nested completion string instead of text
+            let optional = match kind {
+                CXCompletionChunk_Optional => {
+                    let nested = unsafe { clang_getCompletionChunkCompletionString(*completion, idx) };
+                    CompletionChunk::chunks_for(&nested)
+                },
+                _ => Vec::new()
+            };
+
+            chunks.push(CompletionChunk { kind: kind, text: text.to_str(), optional: optional });
+        }
+        chunks
+    }
 }
 
 impl fmt::Show for CompletionChunk {
@@ -32,6 +58,64 @@ impl fmt::Show for CompletionChunk {
     }
 }
 
+/**
+ * Snippet Rendering
+ **/
+
+// UltiSnips uses the same syntax for tab stops and escapes, so yas snippets work there as well
+pub enum SnippetFormat {
+    YasSnippet,
+    LspSnippet
+}
+
+impl SnippetFormat {
+    pub fn from_str(format: &str) -> Option<SnippetFormat> {
+        match format {
+            "yas" => Some(YasSnippet),
+            "lsp" => Some(LspSnippet),
+            _ => None
+        }
+    }
+}
+
+fn escape_snippet_text(text: &str, format: SnippetFormat) -> ~str {
+    let special = match format {
+        YasSnippet => ["\\", "$", "`"],
+        LspSnippet => ["\\", "$", "}"]
+    };
+    let mut result = text.to_owned();
+    for c in special.iter() { result = result.replace(*c, "\\" + *c); }
+    result
+}
+
+// Optional chunks become a single placeholder, so they can be skipped or removed at once
+fn render_chunks(chunks: &[CompletionChunk], format: SnippetFormat, tab_stop: &mut uint,
+                 in_placeholder: bool) -> ~str {
+    let mut snippet = StrBuf::new();
+    for chunk in chunks.iter() {
+        match chunk.kind {
+            CXCompletionChunk_ResultType | CXCompletionChunk_Informative => (),
+            CXCompletionChunk_Placeholder | CXCompletionChunk_CurrentParameter if !in_placeholder => {
+                *tab_stop += 1;
+                let text = escape_snippet_text(chunk.text, format);
+                snippet.push_str("${" + tab_stop.to_str() + ":" + text + "}");
+            },
+            CXCompletionChunk_Optional if !in_placeholder => {
+                *tab_stop += 1;
+                let current = *tab_stop;
+                let text = render_chunks(chunk.optional.as_slice(), format, tab_stop, true);
+                snippet.push_str("${" + current.to_str() + ":" + text + "}");
+            },
+            CXCompletionChunk_Optional => {
+                snippet.push_str(render_chunks(chunk.optional.as_slice(), format, tab_stop, true));
+            },
+            CXCompletionChunk_VerticalSpace => snippet.push_str("\n"),
+            _ => snippet.push_str(escape_snippet_text(chunk.text, format))
+        };
+    }
+    snippet.into_owned()
+}
+
 /**
  * Completion Results
  **/
@@ -40,6 +124,7 @@ pub struct CompletionResult {
     pub priority:     uint,
     pub comment:      ~str,
     pub availability: CXAvailabilityKind,
+    pub parent:       ~str,
     chunks:       Vec<CompletionChunk>
 }
 
@@ -48,42 +133,81 @@ impl CompletionResult {
         let priority     = unsafe { clang_getCompletionPriority(*completion) };
         let availability = unsafe { clang_getCompletionAvailability(*completion) };
         let comment      = unsafe { clang_getCompletionBriefComment(*completion) };
+        let parent_kind: c_uint = 0;
+        let parent       = unsafe { clang_getCompletionParent(*completion, &parent_kind) };
 
-        let mut chunks = Vec::new();
-        let num_chunks = unsafe { clang_getNumCompletionChunks(*completion) };
-        for idx in range(0, num_chunks) {
-            let kind = unsafe { clang_getCompletionChunkKind(*completion, idx) };
-            let text = unsafe { clang_getCompletionChunkText(*completion, idx) };
-
-            chunks.push(CompletionChunk {
-                kind: enum_set::CLike::from_uint(kind as uint),
-                text: text.to_str()
-            });
-        }
+        let chunks = CompletionChunk::chunks_for(completion);
 
         CompletionResult {
             priority:     priority as uint,
             comment:      comment.to_str(),
             availability: enum_set::CLike::from_uint(availability as uint),
+            parent:       parent.to_str(),
             chunks:       chunks
         }
     }
 
-    pub fn to_yas(&self) -> ~str {
-        let mut return_value = StrBuf::from_str("");
-        let mut snippet = StrBuf::from_str("");
+    // Text user is expected to type, for Objective-C methods includes all selector pieces
+    pub fn typed_text(&self) -> ~str {
+        let mut typed_text = StrBuf::new();
+        for chunk in self.chunks.iter() {
+            match chunk.kind {
+                CXCompletionChunk_TypedText => typed_text.push_str(chunk.text),
+                _ => ()
+            };
+        }
+        typed_text.into_owned()
+    }
+
+    pub fn result_type(&self) -> ~str {
+        match self.chunks.iter().find(|chunk| chunk.kind == CXCompletionChunk_ResultType) {
+            Some(chunk) => chunk.text.clone(),
+            None => ~""
+        }
+    }
+
+    pub fn chunks<'a>(&'a self) -> &'a [CompletionChunk] {
+        self.chunks.as_slice()
+    }
+
+    // Placeholders of function arguments or selector pieces, in order
+    pub fn parameters(&self) -> Vec<~str> {
+        self.chunks.iter().filter(|chunk| {
+            chunk.kind == CXCompletionChunk_Placeholder || chunk.kind == CXCompletionChunk_CurrentParameter
+        }).map(|chunk| chunk.text.clone()).collect()
+    }
+
+    // Index of the argument under cursor, only set for overload candidates of a call
+    pub fn current_parameter(&self) -> Option<uint> {
+        self.chunks.iter().filter(|chunk| {
+            chunk.kind == CXCompletionChunk_Placeholder || chunk.kind == CXCompletionChunk_CurrentParameter
+        }).position(|chunk| chunk.kind == CXCompletionChunk_CurrentParameter)
+    }
+
+    // Declaration without optional chunks, ex: `CGRect CGRectMake(CGFloat x, CGFloat y)`
+    pub fn signature(&self) -> ~str {
+        let mut signature = StrBuf::new();
         for chunk in self.chunks.iter() {
             match chunk.kind {
-                CXCompletionChunk_ResultType  => return_value.push_str(chunk.text),
-                CXCompletionChunk_Placeholder => snippet.push_str("${" + chunk.text + "}"),
-                _ => snippet.push_str(chunk.text)
+                CXCompletionChunk_ResultType => signature.push_str(chunk.text + " "),
+                CXCompletionChunk_Informative | CXCompletionChunk_Optional => (),
+                _ => signature.push_str(chunk.text)
             };
         }
+        signature.into_owned()
+    }
+
+    pub fn to_snippet(&self, format: SnippetFormat) -> ~str {
+        let mut tab_stop = 0;
+        let snippet = render_chunks(self.chunks.as_slice(), format, &mut tab_stop, false);
+        match format {
+            YasSnippet => snippet,
+            LspSnippet => snippet + "$0"
+        }
+    }
 
-        let mut result = snippet.clone();
-        result.push_str("\t");
-        result.push_str(return_value.into_owned());
-        result.into_owned()
+    pub fn to_yas(&self) -> ~str {
+        self.to_snippet(YasSnippet) + "\t" + self.result_type()
     }
 }
 
@@ -95,3 +219,114 @@ impl fmt::Show for CompletionResult {
         f.buf.write(chunks_text.connect("").as_bytes())
     }
 }
+
+/**
+ * Completion Results with context
+ **/
+
+pub struct CompletionResults {
+    pub results:              Vec<CompletionResult>,
+    pub contexts:             u64,
+    pub container_kind:       uint,
+    pub container_kind_name:  ~str,
+    pub container_usr:        ~str,
+    pub container_incomplete: bool
+}
+
+impl CompletionResults {
+    pub fn new(completions: *CXCodeCompleteResults) -> CompletionResults {
+        let completion_vector = unsafe {
+            slice::raw::from_buf_raw((*completions).results, (*completions).num_results as uint)
+        };
+        let results = completion_vector.iter().map(|completion| {
+            CompletionResult::new(&completion.completion_string)
+        }).collect();
+
+        let is_incomplete: c_uint = 0;
+        let contexts       = unsafe { clang_codeCompleteGetContexts(completions) };
+        let container_kind = unsafe { clang_codeCompleteGetContainerKind(completions, &is_incomplete) };
+        let kind_name      = unsafe { clang_getCursorKindSpelling(container_kind) };
+        let container_usr  = unsafe { clang_codeCompleteGetContainerUSR(completions) };
+
+        CompletionResults {
+            results:              results,
+            contexts:             contexts as u64,
+            container_kind:       container_kind as uint,
+            container_kind_name:  kind_name.to_str(),
+            container_usr:        container_usr.to_str(),
+            container_incomplete: is_incomplete != 0
+        }
+    }
+
+    pub fn has_context(&self, context: CXCompletionContext) -> bool {
+        self.contexts & context as u64 != 0
+    }
+
+    pub fn iter<'a>(&'a self) -> slice::Items<'a, CompletionResult> {
+        self.results.iter()
+    }
+
+    pub fn len(&self) -> uint {
+        self.results.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use types::*;
+    use code_completion::{CompletionChunk, CompletionResult, YasSnippet, LspSnippet};
+
+    fn chunk(kind: CXCompletionChunkKind, text: &str) -> CompletionChunk {
+        CompletionChunk { kind: kind, text: text.to_owned(), optional: Vec::new() }
+    }
+
+    fn result(chunks: Vec<CompletionChunk>) -> CompletionResult {
+        CompletionResult { priority: 0, comment: ~"", availability: CXAvailability_Available,
+                           parent: ~"", chunks: chunks }
+    }
+
+    #[test]
+    fn selector_snippet() {
+        let completion = result(vec!(chunk(CXCompletionChunk_ResultType, "id"),
+                                     chunk(CXCompletionChunk_TypedText, "initWithFrame:"),
+                                     chunk(CXCompletionChunk_Placeholder, "CGRect"),
+                                     chunk(CXCompletionChunk_HorizontalSpace, " "),
+                                     chunk(CXCompletionChunk_TypedText, "style:"),
+                                     chunk(CXCompletionChunk_Placeholder, "UITableViewStyle")));
+
+        assert!(completion.typed_text() == ~"initWithFrame:style:");
+        assert!(completion.result_type() == ~"id");
+        assert!(completion.to_yas() == ~"initWithFrame:${1:CGRect} style:${2:UITableViewStyle}\tid");
+        assert!(completion.to_snippet(LspSnippet) == ~"initWithFrame:${1:CGRect} style:${2:UITableViewStyle}$0");
+    }
+
+    #[test]
+    fn optional_snippet() {
+        let mut optional = chunk(CXCompletionChunk_Optional, "");
+        optional.optional = vec!(chunk(CXCompletionChunk_Comma, ", "),
+                                 chunk(CXCompletionChunk_Placeholder, "int b"));
+        let completion = result(vec!(chunk(CXCompletionChunk_TypedText, "sum"),
+                                     chunk(CXCompletionChunk_LeftParen, "("),
+                                     chunk(CXCompletionChunk_Placeholder, "int a"),
+                                     optional,
+                                     chunk(CXCompletionChunk_RightParen, ")")));
+
+        assert!(completion.to_snippet(YasSnippet) == ~"sum(${1:int a}${2:, int b})");
+    }
+
+    #[test]
+    fn call_signature() {
+        let completion = result(vec!(chunk(CXCompletionChunk_ResultType, "CGRect"),
+                                     chunk(CXCompletionChunk_TypedText, "CGRectMake"),
+                                     chunk(CXCompletionChunk_LeftParen, "("),
+                                     chunk(CXCompletionChunk_Placeholder, "CGFloat x"),
+                                     chunk(CXCompletionChunk_Comma, ", "),
+                                     chunk(CXCompletionChunk_CurrentParameter, "CGFloat y"),
+                                     chunk(CXCompletionChunk_RightParen, ")")));
+
+        assert!(completion.signature() == ~"CGRect CGRectMake(CGFloat x, CGFloat y)");
+        assert!(completion.parameters() == vec!(~"CGFloat x", ~"CGFloat y"));
+        assert!(completion.current_parameter() == Some(1));
+    }
+}
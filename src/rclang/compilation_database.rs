@@ -3,6 +3,7 @@ use libc::c_char;
 use ffi::*;
 use types::*;
 use translation_unit::TranslationUnit;
+use argument_rewriter::ArgumentRewriter;
 
 /**
  * Compilation Data
@@ -10,20 +11,32 @@ use translation_unit::TranslationUnit;
 
 pub struct CompilationCommand {
     pub cwd: ~str,
+    pub compiler: ~str,
     pub args: Vec<~str>
 }
 
 impl CompilationCommand {
-    pub fn new(compile_command: &CXCompileCommand) -> CompilationCommand {
+    pub fn new(compile_command: &CXCompileCommand, file_path: &Path,
+               rewriter: &ArgumentRewriter) -> CompilationCommand {
         let mut args = Vec::new();
         unsafe {
             let num_args = clang_CompileCommand_getNumArgs(*compile_command);
-            for idx in range(1, num_args - 4) {
+            for idx in range(0, num_args) {
                 args.push(clang_CompileCommand_getArg(*compile_command, idx).to_str());
             }
         }
-        let cwd = unsafe { clang_CompileCommand_getDirectory(*compile_command) };
-        CompilationCommand { args: args, cwd: cwd.to_str() }
+        let cwd = unsafe { clang_CompileCommand_getDirectory(*compile_command) }.to_str();
+
+        // Compiler is rewritten along with arguments, but libclang expects it to be omitted
+        let mut args = rewriter.rewrite(file_path, &Path::new(cwd.as_slice()), args);
+        let compiler = args.shift().unwrap_or(~"");
+        CompilationCommand { args: args, compiler: compiler, cwd: cwd }
+    }
+
+    // SDK headers are resolved against the sysroot
+    pub fn sysroot(&self) -> Option<~str> {
+        let position = self.args.iter().position(|arg| arg.as_slice() == "-isysroot");
+        position.and_then(|idx| self.args.as_slice().get(idx + 1)).map(|sysroot| sysroot.clone())
     }
 
     pub fn args_as_c_vec(&self) -> Vec<*c_char> {
@@ -41,7 +54,8 @@ impl CompilationCommand {
  **/
 
 pub struct CompilationDatabase {
-    cx_c_db : CXCompilationDatabase
+    cx_c_db : CXCompilationDatabase,
+    rewriter: ArgumentRewriter
 }
 
 impl Drop for CompilationDatabase {
@@ -62,7 +76,11 @@ impl CompilationDatabase {
             return Err(CXCompilationDatabase_CanNotLoadDatabase);
         }
 
-        Ok(CompilationDatabase { cx_c_db: cx_c_db })
+        Ok(CompilationDatabase { cx_c_db: cx_c_db, rewriter: ArgumentRewriter::with_default_rules() })
+    }
+
+    pub fn set_argument_rewriter(&mut self, rewriter: ArgumentRewriter) {
+        self.rewriter = rewriter;
     }
 
     pub fn compilation_command_for(&self, file_name: &Path) -> Option<CompilationCommand> {
@@ -78,13 +96,31 @@ impl CompilationDatabase {
             }
 
             let command = clang_CompileCommands_getCommand(compile_commands, 0);
-            CompilationCommand::new(&command)
+            CompilationCommand::new(&command, file_name, &self.rewriter)
         };
 
         unsafe { clang_CompileCommands_dispose(compile_commands); }
         Some(compilation_args)
     }
 
+    // Source files of all commands in the database
+    pub fn files(&self) -> Vec<Path> {
+        let mut files = Vec::new();
+        unsafe {
+            let compile_commands = clang_CompilationDatabase_getAllCompileCommands(self.cx_c_db);
+            let num_commands = clang_CompileCommands_getSize(compile_commands);
+            for idx in range(0, num_commands) {
+                let command = clang_CompileCommands_getCommand(compile_commands, idx);
+                let directory = clang_CompileCommand_getDirectory(command).to_str();
+                let file_name = clang_CompileCommand_getFilename(command).to_str();
+                let file_path = Path::new(directory).join(file_name);
+                if !files.contains(&file_path) { files.push(file_path); }
+            }
+            clang_CompileCommands_dispose(compile_commands);
+        }
+        files
+    }
+
     pub fn translation_unit_for(&self, file_path: &Path) -> Option<TranslationUnit> {
         let result = self.compilation_command_for(file_path);
         match result {
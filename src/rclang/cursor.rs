@@ -0,0 +1,152 @@
+use libc::c_uint;
+use std::{cast, ptr, slice};
+
+use types::*;
+use source_location::{SourceLocation, SourceRange};
+use ffi::*;
+
+/**
+ * Cursor
+ **/
+
+pub struct Cursor {
+    cx_cursor: CXCursor
+}
+
+// Collects direct children, recursion is left to the caller
+extern "C" fn collect_children(cursor: CXCursor, _parent: CXCursor, data: CXClientData) -> c_uint {
+    let children: &mut Vec<Cursor> = unsafe { cast::transmute(data) };
+    children.push(Cursor::new(cursor));
+    CXChildVisit_Continue as c_uint
+}
+
+impl Cursor {
+    pub fn new(cx_cursor: CXCursor) -> Cursor {
+        Cursor { cx_cursor: cx_cursor }
+    }
+
+    pub fn cx_cursor(&self) -> CXCursor {
+        self.cx_cursor
+    }
+
+    pub fn kind(&self) -> c_uint {
+        self.cx_cursor.kind
+    }
+
+    pub fn kind_spelling(&self) -> ~str {
+        unsafe { clang_getCursorKindSpelling(self.cx_cursor.kind) }.to_str()
+    }
+
+    pub fn spelling(&self) -> ~str {
+        unsafe { clang_getCursorSpelling(self.cx_cursor) }.to_str()
+    }
+
+    // Unified symbol resolution, the same for declarations of the entity in all files
+    pub fn usr(&self) -> ~str {
+        unsafe { clang_getCursorUSR(self.cx_cursor) }.to_str()
+    }
+
+    pub fn is_null(&self) -> bool {
+        unsafe { clang_Cursor_isNull(self.cx_cursor) != 0 }
+    }
+
+    pub fn location(&self) -> SourceLocation {
+        unsafe { clang_getCursorLocation(self.cx_cursor) }.expansion_location()
+    }
+
+    // Where the name is written, inside of the macro definition for declarations produced by macros
+    pub fn spelling_location(&self) -> SourceLocation {
+        unsafe { clang_getCursorLocation(self.cx_cursor) }.spelling_location()
+    }
+
+    pub fn extent(&self) -> SourceRange {
+        unsafe { clang_getCursorExtent(self.cx_cursor) }.expansion_range()
+    }
+
+    pub fn referenced(&self) -> Cursor {
+        Cursor::new(unsafe { clang_getCursorReferenced(self.cx_cursor) })
+    }
+
+    pub fn definition(&self) -> Cursor {
+        Cursor::new(unsafe { clang_getCursorDefinition(self.cx_cursor) })
+    }
+
+    // Class for methods, containing declaration for nested declarations
+    pub fn semantic_parent(&self) -> Cursor {
+        Cursor::new(unsafe { clang_getCursorSemanticParent(self.cx_cursor) })
+    }
+
+    pub fn is_definition(&self) -> bool {
+        unsafe { clang_isCursorDefinition(self.cx_cursor) != 0 }
+    }
+
+    // Methods this method directly overrides, for Objective-C includes methods
+    // declared in superclasses, adopted protocols and categories
+    pub fn overridden(&self) -> Vec<Cursor> {
+        let cx_overridden: *CXCursor = ptr::null();
+        let num_overridden: c_uint = 0;
+        unsafe { clang_getOverriddenCursors(self.cx_cursor, &cx_overridden, &num_overridden) };
+        if cx_overridden.is_null() { return Vec::new(); }
+
+        let cursors = unsafe { slice::raw::from_buf_raw(cx_overridden, num_overridden as uint) };
+        let overridden = cursors.iter().map(|&cursor| Cursor::new(cursor)).collect();
+        unsafe { clang_disposeOverriddenCursors(cx_overridden) };
+        overridden
+    }
+
+    // Type kind of the message receiver, CXType_Invalid for other cursors
+    pub fn receiver_type_kind(&self) -> c_uint {
+        unsafe { clang_Cursor_getReceiverType(self.cx_cursor) }.kind
+    }
+
+    pub fn type_spelling(&self) -> ~str {
+        unsafe { clang_getTypeSpelling(clang_getCursorType(self.cx_cursor)) }.to_str()
+    }
+
+    // Mask of CXObjCPropertyAttr_* values, including implicit ones
+    pub fn property_attributes(&self) -> c_uint {
+        unsafe { clang_Cursor_getObjCPropertyAttributes(self.cx_cursor, 0) }
+    }
+
+    pub fn property_getter_name(&self) -> ~str {
+        unsafe { clang_Cursor_getObjCPropertyGetterName(self.cx_cursor) }.to_str()
+    }
+
+    pub fn property_setter_name(&self) -> ~str {
+        unsafe { clang_Cursor_getObjCPropertySetterName(self.cx_cursor) }.to_str()
+    }
+
+    // Mask of CXObjCDeclQualifier_* values of the method or parameter
+    pub fn decl_qualifiers(&self) -> c_uint {
+        unsafe { clang_Cursor_getObjCDeclQualifiers(self.cx_cursor) }
+    }
+
+    // Method or property declared in @optional section of the protocol
+    pub fn is_optional(&self) -> bool {
+        unsafe { clang_Cursor_isObjCOptional(self.cx_cursor) != 0 }
+    }
+
+    // Index of the selector piece the cursor was requested at with TranslationUnit::cursor_at
+    pub fn selector_index(&self) -> Option<uint> {
+        let index = unsafe { clang_Cursor_getObjCSelectorIndex(self.cx_cursor) };
+        if index < 0 { None } else { Some(index as uint) }
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        unsafe { clang_Cursor_isVariadic(self.cx_cursor) != 0 }
+    }
+
+    pub fn children(&self) -> Vec<Cursor> {
+        let mut children: Vec<Cursor> = Vec::new();
+        unsafe {
+            clang_visitChildren(self.cx_cursor, collect_children, cast::transmute(&mut children));
+        }
+        children
+    }
+}
+
+impl Eq for Cursor {
+    fn eq(&self, other: &Cursor) -> bool {
+        unsafe { clang_equalCursors(self.cx_cursor, other.cx_cursor) != 0 }
+    }
+}
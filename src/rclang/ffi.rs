@@ -1,8 +1,8 @@
-use libc::{c_char, c_int, c_uint};
+use libc::{c_char, c_int, c_uint, c_ulonglong};
 
 use types::*;
 use cx_string::CXString;
-use source_location::CXSourceLocation;
+use source_location::{CXSourceLocation, CXSourceRange};
 
 #[link(name = "clang")]
 extern {
@@ -17,6 +17,8 @@ extern {
     pub fn clang_CompilationDatabase_getCompileCommands(c_db: CXCompilationDatabase,
                                                         complete_file_name: *c_char)
                                                         -> CXCompileCommands;
+    pub fn clang_CompilationDatabase_getAllCompileCommands(c_db: CXCompilationDatabase)
+                                                           -> CXCompileCommands;
 
     /**
      * Compilation Command
@@ -28,6 +30,7 @@ extern {
                                             -> CXCompileCommand;
 
     pub fn clang_CompileCommand_getDirectory(command: CXCompileCommand) -> CXString;
+    pub fn clang_CompileCommand_getFilename(command: CXCompileCommand) -> CXString;
     pub fn clang_CompileCommand_getNumArgs(command: CXCompileCommand) -> c_uint;
     pub fn clang_CompileCommand_getArg(command: CXCompileCommand, i: c_uint) -> CXString;
 
@@ -50,6 +53,24 @@ extern {
                                       options: c_uint) -> CXTranslationUnit;
 
     pub fn clang_defaultEditingTranslationUnitOptions() -> c_uint;
+    pub fn clang_disposeTranslationUnit(tu: CXTranslationUnit);
+
+    pub fn clang_defaultReparseOptions(tu: CXTranslationUnit) -> c_uint;
+    pub fn clang_reparseTranslationUnit(tu: CXTranslationUnit,
+                                        num_unsaved_files: c_uint,
+                                        unsaved_files: *CXUnsavedFile,
+                                        options: c_uint) -> c_int;
+
+    pub fn clang_defaultSaveOptions(tu: CXTranslationUnit) -> c_uint;
+    pub fn clang_saveTranslationUnit(tu: CXTranslationUnit,
+                                     file_name: *c_char,
+                                     options: c_uint) -> c_int;
+    pub fn clang_createTranslationUnit(c_idx: CXIndex,
+                                       ast_filename: *c_char) -> CXTranslationUnit;
+
+    pub fn clang_getCXTUResourceUsage(tu: CXTranslationUnit) -> CXTUResourceUsage;
+    pub fn clang_disposeCXTUResourceUsage(usage: CXTUResourceUsage);
+    pub fn clang_getTUResourceUsageName(kind: c_uint) -> *c_char;
 
     /**
      * Code Completion
@@ -63,6 +84,10 @@ extern {
                                 options: c_uint) -> *CXCodeCompleteResults;
     pub fn clang_defaultCodeCompleteOptions() -> c_uint;
     pub fn clang_disposeCodeCompleteResults(results: *CXCodeCompleteResults);
+    pub fn clang_codeCompleteGetContexts(results: *CXCodeCompleteResults) -> c_ulonglong;
+    pub fn clang_codeCompleteGetContainerKind(results: *CXCodeCompleteResults,
+                                              is_incomplete: *c_uint) -> c_uint;
+    pub fn clang_codeCompleteGetContainerUSR(results: *CXCodeCompleteResults) -> CXString;
 
     /**
      * Code Completion Result
@@ -75,6 +100,10 @@ extern {
                                         chunk_number: c_uint) -> c_uint;
     pub fn clang_getCompletionChunkText(completion_string: CXCompletionString,
                                         chunk_number: c_uint) -> CXString;
+    pub fn clang_getCompletionChunkCompletionString(completion_string: CXCompletionString,
+                                                    chunk_number: c_uint) -> CXCompletionString;
+    pub fn clang_getCompletionParent(completion_string: CXCompletionString,
+                                     kind: *c_uint) -> CXString;
 
     /**
      * Diagnostics
@@ -108,6 +137,8 @@ extern {
                              file: CXFile,
                              line: c_uint,
                              column: c_uint) -> CXSourceLocation;
+    pub fn clang_getRangeStart(range: CXSourceRange) -> CXSourceLocation;
+    pub fn clang_getRangeEnd(range: CXSourceRange) -> CXSourceLocation;
 
     /**
      * Cursor
@@ -116,11 +147,50 @@ extern {
     pub fn clang_getCursorDefinition(cursor: CXCursor) -> CXCursor;
     pub fn clang_getCursorReferenced(cursor: CXCursor) -> CXCursor;
     pub fn clang_getCursorLocation(cursor: CXCursor) -> CXSourceLocation;
+    pub fn clang_getCursorKindSpelling(kind: c_uint) -> CXString;
+    pub fn clang_getCursorExtent(cursor: CXCursor) -> CXSourceRange;
+    pub fn clang_getTranslationUnitCursor(tu: CXTranslationUnit) -> CXCursor;
+    pub fn clang_getNullCursor() -> CXCursor;
+    pub fn clang_Cursor_isNull(cursor: CXCursor) -> c_int;
+    pub fn clang_equalCursors(a: CXCursor, b: CXCursor) -> c_uint;
+    pub fn clang_getCursorSpelling(cursor: CXCursor) -> CXString;
+    pub fn clang_getCursorUSR(cursor: CXCursor) -> CXString;
+    pub fn clang_getCursorSemanticParent(cursor: CXCursor) -> CXCursor;
+    pub fn clang_isCursorDefinition(cursor: CXCursor) -> c_uint;
+    pub fn clang_getOverriddenCursors(cursor: CXCursor, overridden: **CXCursor, num_overridden: *c_uint);
+    pub fn clang_disposeOverriddenCursors(overridden: *CXCursor);
+    pub fn clang_Cursor_getReceiverType(cursor: CXCursor) -> CXType;
+    pub fn clang_getCursorType(cursor: CXCursor) -> CXType;
+    pub fn clang_getTypeSpelling(cx_type: CXType) -> CXString;
+    pub fn clang_Cursor_getObjCPropertyAttributes(cursor: CXCursor, reserved: c_uint) -> c_uint;
+    pub fn clang_Cursor_getObjCPropertyGetterName(cursor: CXCursor) -> CXString;
+    pub fn clang_Cursor_getObjCPropertySetterName(cursor: CXCursor) -> CXString;
+    pub fn clang_Cursor_getObjCDeclQualifiers(cursor: CXCursor) -> c_uint;
+    pub fn clang_Cursor_isObjCOptional(cursor: CXCursor) -> c_uint;
+    pub fn clang_Cursor_getObjCSelectorIndex(cursor: CXCursor) -> c_int;
+    pub fn clang_Cursor_isVariadic(cursor: CXCursor) -> c_uint;
+    pub fn clang_visitChildren(parent: CXCursor, visitor: CXCursorVisitor,
+                               client_data: CXClientData) -> c_uint;
+
+    /**
+     * Token
+     **/
+    pub fn clang_tokenize(tu: CXTranslationUnit, range: CXSourceRange,
+                          tokens: **CXToken, num_tokens: *c_uint);
+    pub fn clang_annotateTokens(tu: CXTranslationUnit, tokens: *CXToken,
+                                num_tokens: c_uint, cursors: *mut CXCursor);
+    pub fn clang_disposeTokens(tu: CXTranslationUnit, tokens: *CXToken, num_tokens: c_uint);
+    pub fn clang_getTokenKind(token: CXToken) -> c_uint;
+    pub fn clang_getTokenSpelling(tu: CXTranslationUnit, token: CXToken) -> CXString;
+    pub fn clang_getTokenLocation(tu: CXTranslationUnit, token: CXToken) -> CXSourceLocation;
+    pub fn clang_getTokenExtent(tu: CXTranslationUnit, token: CXToken) -> CXSourceRange;
 
     /**
      * Other
      **/
     pub fn clang_getFile(tu: CXTranslationUnit, file_name: *c_char) -> CXFile;
     pub fn clang_getFileName(file: CXFile) -> CXString;
+    pub fn clang_getInclusions(tu: CXTranslationUnit, visitor: CXInclusionVisitor,
+                               client_data: CXClientData);
     pub fn clang_getCString(string: CXString) -> *c_char;
 }
@@ -12,11 +12,15 @@ extern crate libc;
 
 mod ffi;
 pub mod cx_string;
+pub mod argument_rewriter;
 pub mod compilation_database;
 pub mod translation_unit;
 pub mod source_location;
+pub mod source_file;
+pub mod cursor;
 pub mod code_completion;
 pub mod diagnostic;
+pub mod token;
 
 #[allow(non_camel_case_types)]
 pub mod types;
@@ -24,9 +28,14 @@ pub mod types;
 #[cfg(test)]
 mod tests {
     use std::os;
+    use libc::c_uint;
+    use std::io::File;
 
     use compilation_database::*;
     use translation_unit::*;
+    use argument_rewriter::*;
+    use source_file::*;
+    use types::*;
 
     fn c_db_dir() -> Path {
         os::getcwd().join("./tests/TestApplication")
@@ -63,10 +72,91 @@ mod tests {
         compilation_database();
     }
 
+    #[test]
+    fn database_files() {
+        let files = compilation_database().files();
+        assert!(files.contains(&file_path()));
+        assert!(files.contains(&c_db_dir().join("TestApplication/TestClass.m")));
+    }
+
     #[test]
     fn compile_command() {
         let c_data = compilation_data();
-        assert!(c_data.args.len() == 81);
+        assert!(c_data.args.len() == 74);
+        assert!(!c_data.args.contains(&file_path().as_str().unwrap().to_owned()));
+    }
+
+    #[test]
+    fn default_argument_rules() {
+        let args = vec!(~"-x", ~"objective-c", ~"-MMD", ~"-MT", ~"dependencies", ~"-MF", ~"/baz/A.d",
+                        ~"--serialize-diagnostics", ~"/baz/A.dia", ~"-c", ~"A.m", ~"-o", ~"/baz/A.o");
+        let rewriter = ArgumentRewriter::with_default_rules();
+        let result = rewriter.rewrite(&Path::new("/foo/A.m"), &Path::new("/foo"), args);
+        assert!(result == vec!(~"-x", ~"objective-c"));
+    }
+
+    #[test]
+    fn scoped_argument_rules() {
+        let mut rewriter = ArgumentRewriter::new();
+        rewriter.add_rule(RemapPathPrefix(~"/Applications/Xcode.app", ~"/opt/xcode"));
+        rewriter.add_scoped_rule(Path::new("/foo/Pods"), AddFlag(~"-Wno-everything"));
+
+        let args = || vec!(~"-F/Applications/Xcode.app/Frameworks");
+        let pods_result = rewriter.rewrite(&Path::new("/foo/Pods/B.m"), &Path::new("/foo"), args());
+        assert!(pods_result == vec!(~"-F/opt/xcode/Frameworks", ~"-Wno-everything"));
+
+        let app_result = rewriter.rewrite(&Path::new("/foo/App/A.m"), &Path::new("/foo"), args());
+        assert!(app_result == vec!(~"-F/opt/xcode/Frameworks"));
+    }
+
+    #[test]
+    fn remap_path_prefix() {
+        let mut rewriter = ArgumentRewriter::new();
+        rewriter.add_rule(RemapPathPrefix(~"/Applications/Xcode.app/Contents/Developer", ~"/opt/xcode"));
+
+        let args = vec!(~"/Applications/Xcode.app/Contents/Developer/usr/bin/clang",
+                        ~"-isysroot", ~"/Applications/Xcode.app/Contents/Developer/SDKs/iPhoneSimulator7.1.sdk",
+                        ~"-F/Applications/Xcode.app/Contents/Developer/Library/Frameworks",
+                        ~"-I/foo/Applications/Xcode.app/Contents/Developer");
+        let result = rewriter.rewrite(&Path::new("/foo/A.m"), &Path::new("/foo"), args);
+        assert!(result == vec!(~"/opt/xcode/usr/bin/clang",
+                               ~"-isysroot", ~"/opt/xcode/SDKs/iPhoneSimulator7.1.sdk",
+                               ~"-F/opt/xcode/Library/Frameworks",
+                               ~"-I/foo/Applications/Xcode.app/Contents/Developer"));
+    }
+
+    #[test]
+    fn remap_path_prefix_components() {
+        let mut rewriter = ArgumentRewriter::new();
+        rewriter.add_rule(RemapPathPrefix(~"/Applications/Xcode.app", ~"/opt/xcode"));
+
+        let args = vec!(~"/Applications/Xcode.app", ~"-F/Applications/Xcode.app-beta/Contents",
+                        ~"-isysroot", ~"/Applications/Xcode.app-beta/Contents/SDKs/iPhoneSimulator7.1.sdk");
+        let result = rewriter.rewrite(&Path::new("/foo/A.m"), &Path::new("/foo"), args);
+        assert!(result == vec!(~"/opt/xcode", ~"-F/Applications/Xcode.app-beta/Contents",
+                               ~"-isysroot", ~"/Applications/Xcode.app-beta/Contents/SDKs/iPhoneSimulator7.1.sdk"));
+    }
+
+    #[test]
+    fn source_file_offsets() {
+        let source = SourceFile::new(~"int a;\nint b = sum(a, ");
+        assert!(source.offset_for(2, 13) == Some(19));
+        assert!(source.location_for(19) == Some((2, 13)));
+        assert!(source.offset_for(3, 1).is_none());
+    }
+
+    #[test]
+    fn source_file_columns() {
+        // `ü` takes 2 bytes and `😀` takes 4 bytes or 2 UTF-16 code units
+        let source = SourceFile::new(~"@\"ü😀\"; foo");
+        assert!(source.byte_column(1, 5, CharColumn) == Some(9));
+        assert!(source.byte_column(1, 6, Utf16Column) == Some(9));
+        assert!(source.byte_column(1, 9, ByteColumn) == Some(9));
+        assert!(source.encoded_column(1, 9, CharColumn) == Some(5));
+        assert!(source.encoded_column(1, 9, Utf16Column) == Some(6));
+        assert!(source.encoded_column(1, 4, CharColumn).is_none());
+        assert!(source.location_for(8) == Some((1, 9)));
+        assert!(source.location_for(3).is_none());
     }
 
     #[test]
@@ -75,6 +165,28 @@ mod tests {
         assert!(completions.len() == 39);
     }
 
+    #[test]
+    fn code_completion_with_macros() {
+        let tu = translation_unit();
+        let without_macros = tu.complete_code_with_flags_at(&file_path(), 17, 5, [CXCodeComplete_IncludeCodePatterns]);
+        let with_macros = tu.complete_code_with_flags_at(&file_path(), 17, 5,
+                                                         [CXCodeComplete_IncludeCodePatterns, CXCodeComplete_IncludeMacros]);
+        assert!(with_macros.len() > without_macros.len());
+
+        // Defaults include macros
+        let defaults = tu.complete_code_at(&file_path(), 17, 5);
+        assert!(defaults.len() == tu.complete_code_with_flags_at(&file_path(), 17, 5, [CXCodeComplete_IncludeMacros]).len());
+        assert!(defaults.len() > tu.complete_code_with_flags_at(&file_path(), 17, 5, []).len());
+    }
+
+    #[test]
+    fn code_completion_context() {
+        let completions = translation_unit().complete_code_at(&file_path(), 16, 16);
+        assert!(completions.has_context(CXCompletionContext_ObjCClassMessage));
+        assert!(!completions.has_context(CXCompletionContext_ObjCInstanceMessage));
+        assert!(completions.container_usr == ~"c:objc(cs)TestClass");
+    }
+
     #[test]
     fn diagnostic() {
         let diagnostics = translation_unit().diagnostics();
@@ -85,13 +197,135 @@ mod tests {
     }
 
     #[test]
-    fn go_to_definition() {
-        let source_location = translation_unit().go_to_definition(&compilation_database(), &file_path(), 16, 16);
-        debug!("go_to_definition.file: {}", source_location.file);
-        debug!("go_to_definition.line: {}", source_location.line);
-        debug!("go_to_definition.column: {}", source_location.column);
+    fn parse_options() {
+        let options = ParseOptions::editing().with(CXTranslationUnit_DetailedPreprocessingRecord);
+        assert!(options.contains(CXTranslationUnit_PrecompiledPreamble));
+        assert!(options.contains(CXTranslationUnit_DetailedPreprocessingRecord));
+        assert!(!options.contains(CXTranslationUnit_IncludeBriefCommentsInCodeCompletion));
+
+        let options = options.with(CXTranslationUnit_IncludeBriefCommentsInCodeCompletion)
+                             .without(CXTranslationUnit_DetailedPreprocessingRecord);
+        assert!(options.contains(CXTranslationUnit_IncludeBriefCommentsInCodeCompletion));
+        assert!(!options.contains(CXTranslationUnit_DetailedPreprocessingRecord));
+    }
+
+    #[test]
+    fn resource_usage() {
+        let usage = translation_unit().resource_usage();
+        assert!(usage.len() > 0);
+        assert!(usage.iter().any(|entry| entry.name.starts_with("ASTContext") && entry.amount > 0));
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens = translation_unit().tokens();
+        let message = tokens.iter().find(|token| token.spelling == ~"prepareToTest").unwrap();
+        assert!(message.kind == CXToken_Identifier);
+        assert!(message.location.line == 16);
+        assert!(message.cursor_kind == CXCursor_ObjCMessageExpr as uint);
+
+        let class = tokens.iter().find(|token| token.spelling == ~"TestClass" && token.location.line == 16).unwrap();
+        assert!(class.cursor_kind == CXCursor_ObjCClassRef as uint);
+    }
+
+    #[test]
+    fn enclosing_cursors() {
+        let tu = translation_unit();
+        let range = tu.range_at(&file_path(), (16, 16), (16, 16));
+        let cursors = tu.enclosing_cursors(&range);
+        let kinds: Vec<c_uint> = cursors.iter().map(|cursor| cursor.kind()).collect();
+
+        assert!(kinds.as_slice().starts_with([CXCursor_ObjCImplementationDecl, CXCursor_ObjCInstanceMethodDecl]));
+        assert!(tu.innermost_cursor(&range).unwrap().kind() == CXCursor_ObjCMessageExpr);
+        assert!(cursors.iter().all(|cursor| cursor.extent().contains(&range)));
+    }
+
+    #[test]
+    fn reparse_unsaved_file() {
+        let mut tu = translation_unit();
+        assert!(tu.diagnostics().len() == 1);
+
+        let contents = File::open(&file_path()).read_to_str().unwrap();
+        let unsaved = UnsavedFile { path: file_path(), contents: contents.replace("return YES;", "return YES") };
+        assert!(tu.reparse([unsaved]).is_ok());
+        assert!(tu.diagnostics().len() == 2);
+    }
+
+    #[test]
+    fn included_files() {
+        let files = translation_unit().included_files();
+        assert!(files.contains(&file_path()));
+        assert!(files.contains(&c_db_dir().join("TestApplication/AppDelegate.h")));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let ast_path = os::tmpdir().join("xclang-test-AppDelegate.ast");
+        assert!(translation_unit().save(&ast_path).is_ok());
+
+        let tu = TranslationUnit::from_ast_file(&ast_path);
+        assert!(tu.is_some());
+        let tu = tu.unwrap();
+        let cursor = tu.innermost_cursor(&tu.range_at(&file_path(), (16, 16), (16, 16)));
+        assert!(cursor.unwrap().referenced().spelling() == ~"prepareToTest");
+    }
+
+    #[test]
+    fn sysroot() {
+        let sdk = ~"/Developer/SDKs/iPhoneSimulator7.1.sdk";
+        let command = CompilationCommand { cwd: ~"/foo", compiler: ~"clang", args: vec!(~"-isysroot", sdk.clone(), ~"-fobjc-arc") };
+        assert!(command.sysroot() == Some(sdk));
+
+        let command = CompilationCommand { cwd: ~"/foo", compiler: ~"clang", args: vec!(~"-fobjc-arc") };
+        assert!(command.sysroot().is_none());
+    }
+
+    #[test]
+    fn macro_expansion_cursor() {
+        let options = ParseOptions::editing().with(CXTranslationUnit_DetailedPreprocessingRecord);
+        let tu = TranslationUnit::with_options(&compilation_data(), &file_path(), &options);
+        let expansion = tu.cursor_at(&file_path(), 22, 12);
+        assert!(expansion.kind() == CXCursor_MacroExpansion);
+
+        let definition = expansion.referenced();
+        assert!(definition.kind() == CXCursor_MacroDefinition);
+        assert!(definition.spelling() == ~"YES");
+    }
+
+    #[test]
+    fn property_attributes() {
+        let header = c_db_dir().join("TestApplication/AppDelegate.h");
+        let property = translation_unit().cursor_at(&header, 13, 41);
+        assert!(property.kind() == CXCursor_ObjCPropertyDecl);
+        assert!(property.type_spelling() == ~"UIWindow *");
+
+        let attributes = property.property_attributes();
+        assert!(attributes & CXObjCPropertyAttr_strong != 0);
+        assert!(attributes & CXObjCPropertyAttr_nonatomic != 0);
+        assert!(attributes & CXObjCPropertyAttr_copy == 0);
+        assert!(property.property_getter_name() == ~"window");
+        assert!(property.property_setter_name() == ~"setWindow:");
+        assert!(!property.is_optional());
+    }
+
+    #[test]
+    fn selector_index() {
+        let tu = translation_unit();
+        assert!(tu.cursor_at(&file_path(), 18, 37).selector_index() == Some(0));
+        assert!(tu.cursor_at(&file_path(), 16, 6).selector_index().is_none());
+    }
+
+    #[test]
+    fn referenced_cursor() {
+        let tu = translation_unit();
+        let message = tu.innermost_cursor(&tu.range_at(&file_path(), (16, 16), (16, 16))).unwrap();
+        assert!(message.kind() == CXCursor_ObjCMessageExpr);
+        assert!(message.receiver_type_kind() != CXType_ObjCId);
 
-        assert!(source_location.line == 13);
-        assert!(source_location.column == 9);
+        let method = message.referenced();
+        assert!(method.kind() == CXCursor_ObjCClassMethodDecl);
+        assert!(method.location().file.ends_with("TestClass.h"));
+        assert!(method.location().line == 13);
+        assert!(method.location().column == 9);
     }
 }
@@ -0,0 +1,116 @@
+use std::io::File;
+
+/**
+ * Column Encoding
+ **/
+
+// libclang columns are byte based, editors usually count characters and LSP
+// clients count UTF-16 code units
+pub enum ColumnEncoding {
+    ByteColumn,
+    CharColumn,
+    Utf16Column
+}
+
+impl ColumnEncoding {
+    pub fn from_str(encoding: &str) -> Option<ColumnEncoding> {
+        match encoding {
+            "byte"   => Some(ByteColumn),
+            "char"   => Some(CharColumn),
+            "utf-16" => Some(Utf16Column),
+            _ => None
+        }
+    }
+
+    fn width(&self, c: char) -> uint {
+        match *self {
+            ByteColumn  => c.len_utf8_bytes(),
+            CharColumn  => 1,
+            Utf16Column => if c as u32 > 0xFFFF { 2 } else { 1 }
+        }
+    }
+}
+
+/**
+ * Source File
+ **/
+
+// Conversions between byte offsets and lines with columns, lines and columns are 1-based
+pub struct SourceFile {
+    pub contents: ~str
+}
+
+impl SourceFile {
+    pub fn new(contents: ~str) -> SourceFile {
+        SourceFile { contents: contents }
+    }
+
+    pub fn open(file_path: &Path) -> Result<SourceFile, ~str> {
+        match File::open(file_path).read_to_str() {
+            Ok(contents) => Ok(SourceFile::new(contents)),
+            Err(e) => Err(format!("Unable to read {}: {}", file_path.display(), e))
+        }
+    }
+
+    fn line_start(&self, line: uint) -> Option<uint> {
+        if line == 0 { return None; }
+        if line == 1 { return Some(0); }
+
+        let mut current = 1;
+        for (idx, c) in self.contents.char_indices() {
+            if c != '\n' { continue; }
+            current += 1;
+            if current == line { return Some(idx + 1); }
+        }
+        None
+    }
+
+    fn line_text<'a>(&'a self, line: uint) -> Option<&'a str> {
+        self.line_start(line).map(|start| {
+            let rest = self.contents.slice_from(start);
+            match rest.find('\n') { Some(end) => rest.slice_to(end), None => rest }
+        })
+    }
+
+    pub fn offset_for(&self, line: uint, byte_column: uint) -> Option<uint> {
+        match (self.line_start(line), self.line_text(line)) {
+            (Some(start), Some(text)) if byte_column > 0 && byte_column <= text.len() + 1 => {
+                Some(start + byte_column - 1)
+            },
+            _ => None
+        }
+    }
+
+    // Line and byte column of the offset, offset in the middle of a character has no location
+    pub fn location_for(&self, offset: uint) -> Option<(uint, uint)> {
+        let offset = if offset > self.contents.len() { self.contents.len() } else { offset };
+        if !self.contents.is_char_boundary(offset) { return None; }
+
+        let preceding = self.contents.slice_to(offset);
+        let line = preceding.chars().filter(|&c| c == '\n').count() + 1;
+        let line_start = match preceding.rfind('\n') { Some(idx) => idx + 1, None => 0 };
+        Some((line, offset - line_start + 1))
+    }
+
+    // Column in the middle of a character is rounded to the next one
+    pub fn byte_column(&self, line: uint, column: uint, encoding: ColumnEncoding) -> Option<uint> {
+        let text = match self.line_text(line) { Some(text) => text, None => return None };
+        if column == 0 { return None; }
+
+        let mut units = 0;
+        for (idx, c) in text.char_indices() {
+            if units >= column - 1 { return Some(idx + 1); }
+            units += encoding.width(c);
+        }
+        if units >= column - 1 { Some(text.len() + 1) } else { None }
+    }
+
+    pub fn encoded_column(&self, line: uint, byte_column: uint, encoding: ColumnEncoding) -> Option<uint> {
+        let text = match self.line_text(line) { Some(text) => text, None => return None };
+        if byte_column == 0 || byte_column > text.len() + 1 || !text.is_char_boundary(byte_column - 1) {
+            return None;
+        }
+
+        Some(text.slice_to(byte_column - 1).chars().fold(1, |column, c| column + encoding.width(c)))
+    }
+}
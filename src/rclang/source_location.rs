@@ -3,10 +3,12 @@ use std::ptr;
 use std::fmt;
 
 use ffi::{clang_getSpellingLocation, clang_getExpansionLocation, clang_getFileName};
+use ffi::{clang_getRangeStart, clang_getRangeEnd};
 
 /**
  * Source Location
  **/
+#[deriving(Clone)]
 pub struct SourceLocation {
     pub file:   ~str,
     pub line:   uint,
@@ -21,6 +23,40 @@ impl fmt::Show for SourceLocation {
     }
 }
 
+/**
+ * Source Range
+ **/
+pub struct SourceRange {
+    pub start: SourceLocation,
+    pub end:   SourceLocation
+}
+
+impl SourceRange {
+    // Ranges from different files never contain each other
+    pub fn contains(&self, other: &SourceRange) -> bool {
+        self.start.file == other.start.file &&
+            self.start.offset <= other.start.offset && self.end.offset >= other.end.offset
+    }
+
+    pub fn len(&self) -> uint {
+        self.end.offset - self.start.offset
+    }
+}
+
+impl Eq for SourceRange {
+    fn eq(&self, other: &SourceRange) -> bool {
+        self.start.file == other.start.file &&
+            self.start.offset == other.start.offset && self.end.offset == other.end.offset
+    }
+}
+
+impl fmt::Show for SourceRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = self.start.to_str() + "-" + self.end.line.to_str() + ":" + self.end.column.to_str();
+        f.buf.write(str.as_bytes())
+    }
+}
+
 /**
  * CXSourceLocation
  **/
@@ -66,3 +102,32 @@ impl CXSourceLocation {
         }
     }
 }
+
+/**
+ * CXSourceRange
+ **/
+
+pub struct CXSourceRange {
+    ptr_data0:      *c_void,
+    ptr_data1:      *c_void,
+    begin_int_data: c_uint,
+    end_int_data:   c_uint
+}
+
+impl CXSourceRange {
+    pub fn start(&self) -> CXSourceLocation {
+        unsafe { clang_getRangeStart(*self) }
+    }
+
+    pub fn end(&self) -> CXSourceLocation {
+        unsafe { clang_getRangeEnd(*self) }
+    }
+
+    pub fn spelling_range(&self) -> SourceRange {
+        SourceRange { start: self.start().spelling_location(), end: self.end().spelling_location() }
+    }
+
+    pub fn expansion_range(&self) -> SourceRange {
+        SourceRange { start: self.start().expansion_location(), end: self.end().expansion_location() }
+    }
+}
@@ -0,0 +1,39 @@
+use collections::enum_set;
+
+use types::*;
+use source_location::SourceLocation;
+use ffi::*;
+
+/**
+ * Token
+ **/
+
+pub struct Token {
+    pub kind:            CXTokenKind,
+    pub spelling:        ~str,
+    pub location:        SourceLocation,
+    // Kinds of the cursor token is annotated with and of the cursor it references
+    pub cursor_kind:     uint,
+    pub referenced_kind: uint
+}
+
+impl Token {
+    pub fn new(tu: CXTranslationUnit, token: &CXToken, cursor: &CXCursor) -> Token {
+        let kind        = unsafe { clang_getTokenKind(*token) };
+        let spelling    = unsafe { clang_getTokenSpelling(tu, *token) };
+        let cx_location = unsafe { clang_getTokenLocation(tu, *token) };
+        let referenced  = unsafe { clang_getCursorReferenced(*cursor) };
+
+        Token {
+            kind:            enum_set::CLike::from_uint(kind as uint),
+            spelling:        spelling.to_str(),
+            location:        cx_location.spelling_location(),
+            cursor_kind:     cursor.kind as uint,
+            referenced_kind: referenced.kind as uint
+        }
+    }
+
+    pub fn len(&self) -> uint {
+        self.spelling.len()
+    }
+}
@@ -1,12 +1,91 @@
-use std::slice;
-use std::ptr;
+use std::{cast, ptr, slice, str};
+use std::c_str::CString;
+use libc::{c_char, c_uint, c_ulong};
 
 use types::*;
 use ffi::*;
-use compilation_database::{CompilationCommand, CompilationDatabase};
-use code_completion::CompletionResult;
+use compilation_database::CompilationCommand;
+use code_completion::CompletionResults;
 use diagnostic::Diagnostic;
-use source_location::SourceLocation;
+use token::Token;
+use source_location::{SourceLocation, SourceRange, CXSourceLocation};
+use cursor::Cursor;
+
+/**
+ * Unsaved File
+ **/
+
+pub struct UnsavedFile {
+    pub path:     Path,
+    pub contents: ~str
+}
+
+// Paths and contents should outlive libclang call, so converted files are only
+// available inside the closure
+fn with_unsaved_files<T>(unsaved_files: &[UnsavedFile], f: |&[CXUnsavedFile]| -> T) -> T {
+    let c_paths: Vec<CString> = unsaved_files.iter().map(|file| file.path.to_c_str()).collect();
+    let c_files: Vec<CXUnsavedFile> = unsaved_files.iter().zip(c_paths.iter()).map(|(file, c_path)| {
+        CXUnsavedFile {
+            filename: c_path.with_ref(|path| path),
+            contents: file.contents.as_ptr() as *c_char,
+            length:   file.contents.len() as c_ulong
+        }
+    }).collect();
+    f(c_files.as_slice())
+}
+
+/**
+ * Parse Options
+ **/
+
+pub struct ParseOptions {
+    flags: u32
+}
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        ParseOptions { flags: CXTranslationUnit_None as u32 }
+    }
+
+    // Options suited for editor: precompiled preamble, brief comments are added
+    // only when they are requested in completion
+    pub fn editing() -> ParseOptions {
+        let default_options = unsafe { clang_defaultEditingTranslationUnitOptions() };
+        ParseOptions { flags: default_options }.with(CXTranslationUnit_PrecompiledPreamble)
+    }
+
+    pub fn with(self, flag: CXTranslationUnit_Flags) -> ParseOptions {
+        ParseOptions { flags: self.flags | flag as u32 }
+    }
+
+    pub fn without(self, flag: CXTranslationUnit_Flags) -> ParseOptions {
+        ParseOptions { flags: self.flags & !(flag as u32) }
+    }
+
+    pub fn contains(&self, flag: CXTranslationUnit_Flags) -> bool {
+        self.flags & flag as u32 != 0
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.flags
+    }
+}
+
+/**
+ * Resource Usage
+ **/
+
+pub struct ResourceUsage {
+    pub name:   ~str,
+    pub amount: uint
+}
+
+// Main file is reported too, as the first inclusion
+extern "C" fn collect_inclusions(file: CXFile, _inclusion_stack: *CXSourceLocation,
+                                 _include_len: c_uint, data: CXClientData) {
+    let files: &mut Vec<Path> = unsafe { cast::transmute(data) };
+    files.push(Path::new(unsafe { clang_getFileName(file) }.to_str()));
+}
 
 /**
  * Translation Unit
@@ -19,14 +98,20 @@ pub struct TranslationUnit {
 
 impl Drop for TranslationUnit {
     fn drop(&mut self) {
-        unsafe { clang_disposeIndex(self.cx_index); }
+        unsafe {
+            clang_disposeTranslationUnit(self.cx_translation_unit);
+            clang_disposeIndex(self.cx_index);
+        }
     }
 }
 
 impl TranslationUnit {
     pub fn new(compilation_command: &CompilationCommand, file_path: &Path) -> TranslationUnit {
-        let default_parse_options = unsafe { clang_defaultEditingTranslationUnitOptions() };
-        let parse_options = default_parse_options | CXTranslationUnit_PrecompiledPreamble as u32;
+        TranslationUnit::with_options(compilation_command, file_path, &ParseOptions::editing())
+    }
+
+    pub fn with_options(compilation_command: &CompilationCommand, file_path: &Path,
+                        options: &ParseOptions) -> TranslationUnit {
         let _file_name = unsafe { file_path.to_c_str().unwrap() };
         let index = unsafe { clang_createIndex(0, 0) };
 
@@ -34,34 +119,100 @@ impl TranslationUnit {
             clang_parseTranslationUnit(index, _file_name,
                                        compilation_command.args_as_c_vec().as_ptr(),
                                        compilation_command.args.len() as i32,
-                                       ptr::null(), 0, parse_options)
+                                       ptr::null(), 0, options.bits())
         };
 
         TranslationUnit { cx_translation_unit: tu, cx_index: index }
     }
 
-    pub fn complete_code_at(&self, file_path: &Path, line: uint, column: uint) -> Vec<CompletionResult> {
-        let _file_name = unsafe { file_path.to_c_str().unwrap() };
+    // Loads translation unit saved with `save`, it can't be reparsed or used for completion
+    pub fn from_ast_file(ast_path: &Path) -> Option<TranslationUnit> {
+        let index = unsafe { clang_createIndex(0, 0) };
+        let tu = ast_path.with_c_str(|_ast_name| {
+            unsafe { clang_createTranslationUnit(index, _ast_name) }
+        });
 
+        if tu.is_null() {
+            unsafe { clang_disposeIndex(index) };
+            return None;
+        }
+        Some(TranslationUnit { cx_translation_unit: tu, cx_index: index })
+    }
+
+    // Reuses precompiled preamble, so only the main file is parsed again
+    pub fn reparse(&mut self, unsaved_files: &[UnsavedFile]) -> Result<(), ~str> {
+        let options = unsafe { clang_defaultReparseOptions(self.cx_translation_unit) };
+        let result = with_unsaved_files(unsaved_files, |c_files| {
+            unsafe {
+                clang_reparseTranslationUnit(self.cx_translation_unit, c_files.len() as u32,
+                                             c_files.as_ptr(), options)
+            }
+        });
+
+        if result == 0 { Ok(()) } else { Err(~"Unable to reparse translation unit") }
+    }
+
+    // Files parsed for the translation unit, including the main file
+    pub fn included_files(&self) -> Vec<Path> {
+        let mut files: Vec<Path> = Vec::new();
+        unsafe {
+            clang_getInclusions(self.cx_translation_unit, collect_inclusions, cast::transmute(&mut files));
+        }
+        files
+    }
+
+    pub fn save(&self, ast_path: &Path) -> Result<(), CXSaveError> {
+        let options = unsafe { clang_defaultSaveOptions(self.cx_translation_unit) };
+        let result = ast_path.with_c_str(|_ast_name| {
+            unsafe { clang_saveTranslationUnit(self.cx_translation_unit, _ast_name, options) }
+        });
+
+        match result {
+            0 => Ok(()),
+            2 => Err(CXSaveError_TranslationErrors),
+            3 => Err(CXSaveError_InvalidTU),
+            _ => Err(CXSaveError_Unknown)
+        }
+    }
+
+    // Memory used by the translation unit in bytes, grouped by kind
+    pub fn resource_usage(&self) -> Vec<ResourceUsage> {
+        let usage = unsafe { clang_getCXTUResourceUsage(self.cx_translation_unit) };
+        let entries = unsafe { slice::raw::from_buf_raw(usage.entries, usage.num_entries as uint) };
+
+        let result = entries.iter().map(|entry| {
+            let name = unsafe { str::raw::from_c_str(clang_getTUResourceUsageName(entry.kind)) };
+            ResourceUsage { name: name, amount: entry.amount as uint }
+        }).collect();
+
+        unsafe { clang_disposeCXTUResourceUsage(usage) };
+        result
+    }
+
+    // Uses libclang default options, macros are included
+    pub fn complete_code_at(&self, file_path: &Path, line: uint, column: uint) -> CompletionResults {
+        let default_options = unsafe { clang_defaultCodeCompleteOptions() };
+        self.complete_code_with_options_at(file_path, line, column, default_options)
+    }
+
+    // Only requested flags are passed instead of libclang defaults, so results
+    // like macros can be turned off
+    pub fn complete_code_with_flags_at(&self, file_path: &Path, line: uint, column: uint,
+                                       flags: &[CXCodeComplete_Flags]) -> CompletionResults {
+        let options = flags.iter().fold(0u32, |options, &flag| options | flag as u32);
+        self.complete_code_with_options_at(file_path, line, column, options)
+    }
+
+    fn complete_code_with_options_at(&self, file_path: &Path, line: uint, column: uint,
+                                     options: c_uint) -> CompletionResults {
+        let _file_name = unsafe { file_path.to_c_str().unwrap() };
         let completions = unsafe {
             clang_codeCompleteAt(self.cx_translation_unit, _file_name,
                                  line as u32, column as u32,
-                                 ptr::null(), 0,
-                                 clang_defaultCodeCompleteOptions())
-        };
-
-        let mut results = Vec::new();
-        let completion_vector = unsafe {
-            slice::raw::from_buf_raw((*completions).results, (*completions).num_results as uint)
+                                 ptr::null(), 0, options)
         };
-        for idx in range(0, completion_vector.len()) {
-            let completion_option = completion_vector.get(idx);
-            match completion_option {
-                Some(completion) => results.push(CompletionResult::new(&completion.completion_string)),
-                _ => ()
-            }
-        }
 
+        let results = CompletionResults::new(completions);
         unsafe { clang_disposeCodeCompleteResults(completions) };
         results
     }
@@ -79,43 +230,71 @@ impl TranslationUnit {
         diagnostics
     }
 
-    pub fn referenced_location(&self, c_db: &CompilationDatabase,
-                               cursor: CXCursor) -> SourceLocation {
+    // Tokens of the main file annotated with cursors they belong to
+    pub fn tokens(&self) -> Vec<Token> {
+        let tu_cursor = unsafe { clang_getTranslationUnitCursor(self.cx_translation_unit) };
+        let extent = unsafe { clang_getCursorExtent(tu_cursor) };
 
-        let referenced_cursor = unsafe { clang_getCursorReferenced(cursor) };
-        let cx_location = unsafe { clang_getCursorLocation(referenced_cursor) };
-        let location = cx_location.expansion_location();
-        let m_file = location.file.replace(".h", ".m");
+        let cx_tokens: *CXToken = ptr::null();
+        let num_tokens: c_uint = 0;
+        unsafe { clang_tokenize(self.cx_translation_unit, extent, &cx_tokens, &num_tokens) };
+        if cx_tokens.is_null() { return Vec::new(); }
 
-        match c_db.translation_unit_for(&Path::new(m_file)) {
-            Some(child_tu) => {
-                let line = location.line;
-                let column = location.column;
-                child_tu.go_to_definition(c_db, &Path::new(location.file), line, column)
-            },
-            None => location
-        }
+        let token_slice = unsafe { slice::raw::from_buf_raw(cx_tokens, num_tokens as uint) };
+        let mut cursors = Vec::from_fn(num_tokens as uint, |_| unsafe { clang_getNullCursor() });
+        unsafe { clang_annotateTokens(self.cx_translation_unit, cx_tokens, num_tokens, cursors.as_mut_ptr()) };
+
+        let tokens = token_slice.iter().zip(cursors.iter()).map(|(token, cursor)| {
+            Token::new(self.cx_translation_unit, token, cursor)
+        }).collect();
+
+        unsafe { clang_disposeTokens(self.cx_translation_unit, cx_tokens, num_tokens) };
+        tokens
     }
 
-    pub fn go_to_definition(&self, c_db: &CompilationDatabase, file_path: &Path,
-                            line: uint, column: uint) -> SourceLocation {
+    pub fn cursor(&self) -> Cursor {
+        Cursor::new(unsafe { clang_getTranslationUnitCursor(self.cx_translation_unit) })
+    }
+
+    // Innermost cursor at the location, macro expansions are reported with detailed preprocessing record
+    pub fn cursor_at(&self, file_path: &Path, line: uint, column: uint) -> Cursor {
+        Cursor::new(unsafe { clang_getCursor(self.cx_translation_unit, self.location_at(file_path, line, column)) })
+    }
 
+    fn location_at(&self, file_path: &Path, line: uint, column: uint) -> CXSourceLocation {
         let file = file_path.with_c_str(|_file_name| {
             unsafe { clang_getFile(self.cx_translation_unit, _file_name) }
         });
-        let location = unsafe { clang_getLocation(self.cx_translation_unit, file,
-                                                  line as u32, column as u32) };
-        let cursor = unsafe { clang_getCursor(self.cx_translation_unit, location) };
-
-        match cursor.kind {
-            104 => { // CXCursor_ObjCMessageExpr
-                self.referenced_location(c_db, cursor)
-            },
-            _  => {
-                let definition_cursor = unsafe { clang_getCursorDefinition(cursor) };
-                let definition_location = unsafe { clang_getCursorLocation(definition_cursor) };
-                definition_location.expansion_location()
+        unsafe { clang_getLocation(self.cx_translation_unit, file, line as u32, column as u32) }
+    }
+
+    // Start and end are (line, column) pairs
+    pub fn range_at(&self, file_path: &Path, start: (uint, uint), end: (uint, uint)) -> SourceRange {
+        let (start_line, start_column) = start;
+        let (end_line, end_column) = end;
+        SourceRange {
+            start: self.location_at(file_path, start_line, start_column).expansion_location(),
+            end:   self.location_at(file_path, end_line, end_column).expansion_location()
+        }
+    }
+
+    // Cursors whose extent covers range, from the outermost to the innermost
+    pub fn enclosing_cursors(&self, range: &SourceRange) -> Vec<Cursor> {
+        let mut cursors = Vec::new();
+        let mut current = self.cursor();
+        loop {
+            match current.children().move_iter().find(|child| child.extent().contains(range)) {
+                Some(child) => {
+                    cursors.push(child);
+                    current = child;
+                },
+                None => break
             }
         }
+        cursors
+    }
+
+    pub fn innermost_cursor(&self, range: &SourceRange) -> Option<Cursor> {
+        self.enclosing_cursors(range).pop()
     }
 }
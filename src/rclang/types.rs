@@ -2,6 +2,8 @@ use libc::{c_void, c_char, c_ulong, c_uint, c_int};
 use collections::enum_set::CLike;
 use std::cast;
 
+use source_location::CXSourceLocation;
+
 /**
  * Compilation Database
  **/
@@ -31,7 +33,33 @@ pub enum CXTranslationUnit_Flags {
     CXTranslationUnit_ForSerialization                     = 0x10,
     CXTranslationUnit_CXXChainedPCH                        = 0x20,
     CXTranslationUnit_SkipFunctionBodies                   = 0x40,
-    CXTranslationUnit_IncludeBriefCommentsInCodeCompletion = 0x80
+    CXTranslationUnit_IncludeBriefCommentsInCodeCompletion = 0x80,
+    CXTranslationUnit_CreatePreambleOnFirstParse           = 0x100,
+    CXTranslationUnit_KeepGoing                            = 0x200,
+    CXTranslationUnit_SingleFileParse                      = 0x400,
+    CXTranslationUnit_LimitSkipFunctionBodiesToPreamble    = 0x800,
+    CXTranslationUnit_IncludeAttributedTypes               = 0x1000,
+    CXTranslationUnit_VisitImplicitAttributes              = 0x2000,
+    CXTranslationUnit_IgnoreNonErrorsFromIncludedFiles     = 0x4000,
+    CXTranslationUnit_RetainExcludedConditionalBlocks      = 0x8000
+}
+
+pub struct CXTUResourceUsageEntry {
+    pub kind:   c_uint,
+    pub amount: c_ulong
+}
+
+pub struct CXTUResourceUsage {
+    pub data:        *c_void,
+    pub num_entries: c_uint,
+    pub entries:     *CXTUResourceUsageEntry
+}
+
+pub enum CXSaveError {
+    CXSaveError_None,
+    CXSaveError_Unknown,
+    CXSaveError_TranslationErrors,
+    CXSaveError_InvalidTU
 }
 
 /**
@@ -41,9 +69,9 @@ pub enum CXTranslationUnit_Flags {
 pub type CXCompletionString = *c_void;
 
 pub struct CXUnsavedFile {
-    filename: *c_char,
-    contents: *c_char,
-    length:   c_ulong
+    pub filename: *c_char,
+    pub contents: *c_char,
+    pub length:   c_ulong
 }
 
 pub struct CXCompletionResult {
@@ -101,11 +129,45 @@ impl CLike for CXCompletionChunkKind {
     fn to_uint(&self) -> uint { *self as uint }
     fn from_uint(v: uint) -> CXCompletionChunkKind { unsafe { cast::transmute(v) } }
 }
+impl Eq for CXCompletionChunkKind {
+    fn eq(&self, other: &CXCompletionChunkKind) -> bool {
+        *self as uint == *other as uint
+    }
+}
 
+// SkipPreamble and IncludeCompletionsWithFixIts require libclang 6.0 or newer
 pub enum CXCodeComplete_Flags {
-    CXCodeComplete_IncludeMacros        = 0x01,
-    CXCodeComplete_IncludeCodePatterns  = 0x02,
-    CXCodeComplete_IncludeBriefComments = 0x04
+    CXCodeComplete_IncludeMacros                = 0x01,
+    CXCodeComplete_IncludeCodePatterns          = 0x02,
+    CXCodeComplete_IncludeBriefComments         = 0x04,
+    CXCodeComplete_SkipPreamble                 = 0x08,
+    CXCodeComplete_IncludeCompletionsWithFixIts = 0x10
+}
+
+pub enum CXCompletionContext {
+    CXCompletionContext_Unexposed           = 0,
+    CXCompletionContext_AnyType             = 1 << 0,
+    CXCompletionContext_AnyValue            = 1 << 1,
+    CXCompletionContext_ObjCObjectValue     = 1 << 2,
+    CXCompletionContext_ObjCSelectorValue   = 1 << 3,
+    CXCompletionContext_CXXClassTypeValue   = 1 << 4,
+    CXCompletionContext_DotMemberAccess     = 1 << 5,
+    CXCompletionContext_ArrowMemberAccess   = 1 << 6,
+    CXCompletionContext_ObjCPropertyAccess  = 1 << 7,
+    CXCompletionContext_EnumTag             = 1 << 8,
+    CXCompletionContext_UnionTag            = 1 << 9,
+    CXCompletionContext_StructTag           = 1 << 10,
+    CXCompletionContext_ClassTag            = 1 << 11,
+    CXCompletionContext_Namespace           = 1 << 12,
+    CXCompletionContext_NestedNameSpecifier = 1 << 13,
+    CXCompletionContext_ObjCInterface       = 1 << 14,
+    CXCompletionContext_ObjCProtocol        = 1 << 15,
+    CXCompletionContext_ObjCCategory        = 1 << 16,
+    CXCompletionContext_ObjCInstanceMessage = 1 << 17,
+    CXCompletionContext_ObjCClassMessage    = 1 << 18,
+    CXCompletionContext_ObjCSelectorName    = 1 << 19,
+    CXCompletionContext_MacroName           = 1 << 20,
+    CXCompletionContext_NaturalLanguage     = 1 << 21
 }
 
 /**
@@ -119,7 +181,6 @@ pub type CXFile       = *c_void;
 pub enum CXDiagnosticSeverity {
     CXDiagnostic_Ignored,
     CXDiagnostic_Note,
-    CXDiagnostic_Remark,
     CXDiagnostic_Warning,
     CXDiagnostic_Error,
     CXDiagnostic_Fatal
@@ -128,6 +189,11 @@ impl CLike for CXDiagnosticSeverity {
     fn to_uint(&self) -> uint { *self as uint }
     fn from_uint(v: uint) -> CXDiagnosticSeverity { unsafe { cast::transmute(v) } }
 }
+impl Eq for CXDiagnosticSeverity {
+    fn eq(&self, other: &CXDiagnosticSeverity) -> bool {
+        *self as uint == *other as uint
+    }
+}
 
 /**
  * Cursor
@@ -140,3 +206,104 @@ pub struct CXCursor {
     data1: *c_void,
     data2: *c_void
 }
+
+pub type CXClientData = *c_void;
+pub type CXCursorVisitor = extern "C" fn(cursor: CXCursor, parent: CXCursor, client_data: CXClientData) -> c_uint;
+pub type CXInclusionVisitor = extern "C" fn(included_file: CXFile, inclusion_stack: *CXSourceLocation,
+                                             include_len: c_uint, client_data: CXClientData);
+
+pub enum CXChildVisitResult {
+    CXChildVisit_Break,
+    CXChildVisit_Continue,
+    CXChildVisit_Recurse
+}
+
+// Cursor kinds are sparse, so they are kept as constants instead of enum
+pub static CXCursor_EnumConstantDecl: c_uint       = 7;
+pub static CXCursor_FunctionDecl: c_uint           = 8;
+pub static CXCursor_ParmDecl: c_uint               = 10;
+pub static CXCursor_ObjCInterfaceDecl: c_uint      = 11;
+pub static CXCursor_ObjCCategoryDecl: c_uint       = 12;
+pub static CXCursor_ObjCProtocolDecl: c_uint       = 13;
+pub static CXCursor_ObjCPropertyDecl: c_uint       = 14;
+pub static CXCursor_ObjCIvarDecl: c_uint           = 15;
+pub static CXCursor_ObjCInstanceMethodDecl: c_uint = 16;
+pub static CXCursor_ObjCClassMethodDecl: c_uint    = 17;
+pub static CXCursor_ObjCImplementationDecl: c_uint = 18;
+pub static CXCursor_ObjCCategoryImplDecl: c_uint   = 19;
+pub static CXCursor_ObjCSuperClassRef: c_uint      = 40;
+pub static CXCursor_ObjCProtocolRef: c_uint        = 41;
+pub static CXCursor_ObjCClassRef: c_uint           = 42;
+pub static CXCursor_DeclRefExpr: c_uint            = 101;
+pub static CXCursor_MemberRefExpr: c_uint          = 102;
+pub static CXCursor_CallExpr: c_uint               = 103;
+pub static CXCursor_ObjCMessageExpr: c_uint        = 104;
+pub static CXCursor_MacroDefinition: c_uint        = 501;
+pub static CXCursor_MacroExpansion: c_uint         = 502;
+
+/**
+ * Type
+ **/
+
+pub struct CXType {
+    pub kind: c_uint,
+    data0: *c_void,
+    data1: *c_void
+}
+
+pub static CXType_Invalid: c_uint = 0;
+pub static CXType_ObjCId: c_uint  = 27;
+
+/**
+ * Objective-C Attributes
+ **/
+
+// Property attributes and method qualifiers are bit masks
+pub static CXObjCPropertyAttr_noattr: c_uint            = 0x00;
+pub static CXObjCPropertyAttr_readonly: c_uint          = 0x01;
+pub static CXObjCPropertyAttr_getter: c_uint            = 0x02;
+pub static CXObjCPropertyAttr_assign: c_uint            = 0x04;
+pub static CXObjCPropertyAttr_readwrite: c_uint         = 0x08;
+pub static CXObjCPropertyAttr_retain: c_uint            = 0x10;
+pub static CXObjCPropertyAttr_copy: c_uint              = 0x20;
+pub static CXObjCPropertyAttr_nonatomic: c_uint         = 0x40;
+pub static CXObjCPropertyAttr_setter: c_uint            = 0x80;
+pub static CXObjCPropertyAttr_atomic: c_uint            = 0x100;
+pub static CXObjCPropertyAttr_weak: c_uint              = 0x200;
+pub static CXObjCPropertyAttr_strong: c_uint            = 0x400;
+pub static CXObjCPropertyAttr_unsafe_unretained: c_uint = 0x800;
+
+pub static CXObjCDeclQualifier_None: c_uint   = 0x00;
+pub static CXObjCDeclQualifier_In: c_uint     = 0x01;
+pub static CXObjCDeclQualifier_Inout: c_uint  = 0x02;
+pub static CXObjCDeclQualifier_Out: c_uint    = 0x04;
+pub static CXObjCDeclQualifier_Bycopy: c_uint = 0x08;
+pub static CXObjCDeclQualifier_Byref: c_uint  = 0x10;
+pub static CXObjCDeclQualifier_Oneway: c_uint = 0x20;
+
+/**
+ * Token
+ **/
+
+#[repr(uint)]
+pub enum CXTokenKind {
+    CXToken_Punctuation,
+    CXToken_Keyword,
+    CXToken_Identifier,
+    CXToken_Literal,
+    CXToken_Comment
+}
+impl CLike for CXTokenKind {
+    fn to_uint(&self) -> uint { *self as uint }
+    fn from_uint(v: uint) -> CXTokenKind { unsafe { cast::transmute(v) } }
+}
+impl Eq for CXTokenKind {
+    fn eq(&self, other: &CXTokenKind) -> bool {
+        *self as uint == *other as uint
+    }
+}
+
+pub struct CXToken {
+    int_data: [c_uint, ..4],
+    ptr_data: *c_void
+}
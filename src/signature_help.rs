@@ -0,0 +1,247 @@
+use std::str;
+
+/**
+ * Call site lookup for signature help
+ **/
+
+#[deriving(Eq, Show)]
+pub enum CallKind {
+    FunctionCall,
+    MessageSend
+}
+
+pub struct CallSite {
+    pub kind:             CallKind,
+    // Offset of the function name or first selector piece, completion is requested there
+    pub name_offset:      uint,
+    // Function name or selector pieces typed so far, ex: `doSomething:with:`
+    pub name:             ~str,
+    pub active_parameter: uint
+}
+
+fn is_identifier_char(c: u8) -> bool {
+    (c as char).is_alphanumeric() || c == '_' as u8
+}
+
+// Marks bytes before `end` that are code, string and character literals and
+// comments are skipped so brackets and separators inside them are ignored
+fn code_mask(source: &[u8], end: uint) -> Vec<bool> {
+    let mut mask = Vec::from_elem(end, true);
+    let mut idx = 0;
+    while idx < end {
+        let c = source[idx] as char;
+        let next = if idx + 1 < end { source[idx + 1] as char } else { '\0' };
+        let start = idx;
+        if c == '/' && next == '/' {
+            while idx < end && source[idx] as char != '\n' { idx += 1; }
+        } else if c == '/' && next == '*' {
+            idx += 3;
+            while idx < end && !(source[idx - 1] as char == '*' && source[idx] as char == '/') { idx += 1; }
+            idx += 1;
+        } else if c == '"' || c == '\'' {
+            idx += 1;
+            while idx < end && source[idx] as char != c && source[idx] as char != '\n' {
+                if source[idx] as char == '\\' { idx += 1; }
+                idx += 1;
+            }
+            idx += 1;
+        } else {
+            idx += 1;
+            continue;
+        }
+        for i in range(start, if idx < end { idx } else { end }) { *mask.get_mut(i) = false; }
+    }
+    mask
+}
+
+// Scans backwards for the innermost unclosed `(` or `[`, skipping nested brackets
+// and block bodies passed as arguments
+fn enclosing_bracket(source: &[u8], mask: &[bool], offset: uint) -> Option<uint> {
+    let mut depth = 0;
+    let mut idx = offset;
+    while idx > 0 {
+        idx -= 1;
+        if !mask[idx] { continue; }
+        match source[idx] as char {
+            ')' | ']' | '}' => depth += 1,
+            '(' | '[' | '{' if depth > 0 => depth -= 1,
+            '(' | '[' => return Some(idx),
+            ';' | '{' if depth == 0 => return None,
+            _ => ()
+        }
+    }
+    None
+}
+
+fn function_call(source: &[u8], mask: &[bool], paren: uint, offset: uint) -> Option<CallSite> {
+    let mut name_start = paren;
+    while name_start > 0 && is_identifier_char(source[name_start - 1]) { name_start -= 1; }
+    if name_start == paren { return None; }
+
+    let mut depth = 0;
+    let mut active_parameter = 0;
+    for idx in range(paren + 1, offset) {
+        if !mask[idx] { continue; }
+        match source[idx] as char {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => active_parameter += 1,
+            _ => ()
+        }
+    }
+
+    Some(CallSite {
+        kind:             FunctionCall,
+        name_offset:      name_start,
+        name:             str::from_utf8_lossy(source.slice(name_start, paren)).into_owned(),
+        active_parameter: active_parameter
+    })
+}
+
+// Receiver is the first expression after `[`, selector pieces are identifiers
+// followed by `:` at the top nesting level
+fn message_send(source: &[u8], mask: &[bool], bracket: uint, offset: uint) -> Option<CallSite> {
+    let mut depth = 0;
+    let mut idx = bracket + 1;
+    let mut receiver_end = None;
+    let mut pieces: Vec<~str> = Vec::new();
+    let mut piece_start = None;
+    let mut first_piece_offset = None;
+    // Set once the argument of the last piece is followed by whitespace
+    let mut argument_ended = false;
+    let mut argument_started = false;
+    let mut new_piece = false;
+
+    while idx < offset {
+        let c = source[idx];
+        if !mask[idx] {
+            argument_started = true;
+            piece_start = None;
+            idx += 1;
+            continue;
+        }
+        match c as char {
+            '(' | '[' | '{' => { depth += 1; piece_start = None; argument_started = true; },
+            ')' | ']' | '}' => { depth -= 1; piece_start = None; },
+            ':' if depth == 0 => {
+                match piece_start {
+                    Some(start) if receiver_end.is_some() => {
+                        if first_piece_offset.is_none() { first_piece_offset = Some(start); }
+                        pieces.push(str::from_utf8_lossy(source.slice(start, idx)).into_owned() + ":");
+                    },
+                    _ => ()
+                }
+                piece_start = None;
+                argument_started = false;
+                argument_ended = false;
+                new_piece = false;
+            },
+            ' ' | '\t' | '\n' if depth == 0 => {
+                if receiver_end.is_none() && idx > bracket + 1 { receiver_end = Some(idx); }
+                if argument_started { argument_ended = true; }
+                piece_start = None;
+                new_piece = false;
+            },
+            _ if depth == 0 && is_identifier_char(c) => {
+                if piece_start.is_none() {
+                    piece_start = Some(idx);
+                    new_piece = argument_ended;
+                }
+                argument_started = true;
+            },
+            _ => { piece_start = None; argument_started = true; }
+        }
+        idx += 1;
+    }
+
+    let name_offset = match (first_piece_offset, receiver_end) {
+        (Some(piece_offset), _) => piece_offset,
+        (None, Some(end)) => end + 1,
+        (None, None) => return None
+    };
+
+    // Cursor is either in the argument of the last typed piece or on the name
+    // of the next piece after a finished argument
+    let active_parameter = match pieces.len() {
+        0 => 0,
+        count if new_piece && piece_start.is_some() => count,
+        count => count - 1
+    };
+
+    Some(CallSite {
+        kind:             MessageSend,
+        name_offset:      name_offset,
+        name:             pieces.as_slice().concat(),
+        active_parameter: active_parameter
+    })
+}
+
+pub fn enclosing_call(source: &str, offset: uint) -> Option<CallSite> {
+    let bytes = source.as_bytes();
+    if offset > bytes.len() { return None; }
+
+    let mask = code_mask(bytes, offset);
+    let bracket = match enclosing_bracket(bytes, mask.as_slice(), offset) { Some(b) => b, None => return None };
+    match bytes[bracket] as char {
+        '(' => function_call(bytes, mask.as_slice(), bracket, offset),
+        _ => message_send(bytes, mask.as_slice(), bracket, offset)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use signature_help::{enclosing_call, FunctionCall, MessageSend};
+
+    #[test]
+    fn function_call_site() {
+        let source = "CGRect frame = CGRectMake(0, MAX(a, b), ";
+        let call = enclosing_call(source, source.len()).unwrap();
+        assert!(call.kind == FunctionCall);
+        assert!(call.name == ~"CGRectMake");
+        assert!(call.name_offset == 15);
+        assert!(call.active_parameter == 2);
+    }
+
+    #[test]
+    fn message_send_site() {
+        let source = "    [self.tableView doSomething:[obj value] with:";
+        let call = enclosing_call(source, source.len()).unwrap();
+        assert!(call.kind == MessageSend);
+        assert!(call.name == ~"doSomething:with:");
+        assert!(call.name_offset == 20);
+        assert!(call.active_parameter == 1);
+    }
+
+    #[test]
+    fn message_send_with_block_argument() {
+        let source = "[obj doWith:^{ [self update]; } andThen:";
+        let call = enclosing_call(source, source.len()).unwrap();
+        assert!(call.kind == MessageSend);
+        assert!(call.name == ~"doWith:andThen:");
+        assert!(call.active_parameter == 1);
+    }
+
+    #[test]
+    fn message_send_next_piece() {
+        let source = "[obj doSomething:value wi";
+        let call = enclosing_call(source, source.len()).unwrap();
+        assert!(call.name == ~"doSomething:");
+        assert!(call.active_parameter == 1);
+    }
+
+    #[test]
+    fn brackets_in_strings_and_comments() {
+        let source = "NSLog(@\"(%@\", /* [ */ value, ";
+        let call = enclosing_call(source, source.len()).unwrap();
+        assert!(call.kind == FunctionCall);
+        assert!(call.name == ~"NSLog");
+        assert!(call.active_parameter == 2);
+    }
+
+    #[test]
+    fn no_call_site() {
+        let source = "{ NSString *value = nil; ";
+        assert!(enclosing_call(source, source.len()).is_none());
+    }
+}
@@ -0,0 +1,194 @@
+use libc::c_uint;
+
+use rclang::cursor::Cursor;
+use rclang::source_location::SourceLocation;
+use rclang::translation_unit::TranslationUnit;
+use rclang::types::*;
+
+/**
+ * Type Hierarchy
+ **/
+
+pub struct TypeEntry {
+    pub relation: &'static str,
+    pub depth:    uint,
+    pub name:     ~str,
+    pub usr:      ~str,
+    pub location: SourceLocation
+}
+
+impl TypeEntry {
+    fn new(relation: &'static str, depth: uint, cursor: &Cursor) -> TypeEntry {
+        TypeEntry { relation: relation, depth: depth, name: display_name(cursor),
+                    usr: cursor.usr(), location: cursor.location() }
+    }
+}
+
+// Categories are shown together with extended class, ex: `UIView (Animations)`
+fn display_name(cursor: &Cursor) -> ~str {
+    match cursor.kind() {
+        CXCursor_ObjCCategoryDecl => match extended_class(cursor) {
+            Some(class) => class.spelling() + " (" + cursor.spelling() + ")",
+            None => cursor.spelling()
+        },
+        _ => cursor.spelling()
+    }
+}
+
+pub fn extended_class(category: &Cursor) -> Option<Cursor> {
+    category.children().move_iter().find(|child| child.kind() == CXCursor_ObjCClassRef)
+                                   .map(|class_ref| class_ref.referenced())
+}
+
+fn is_type_declaration(kind: c_uint) -> bool {
+    match kind {
+        CXCursor_ObjCInterfaceDecl | CXCursor_ObjCProtocolDecl | CXCursor_ObjCImplementationDecl |
+        CXCursor_ObjCCategoryDecl | CXCursor_ObjCCategoryImplDecl => true,
+        _ => false
+    }
+}
+
+// Interface or protocol definition with the USR, forward declarations are
+// reported as references, so top level declarations are definitions
+fn find_definition(tu: &TranslationUnit, usr: &str, kind: c_uint) -> Option<Cursor> {
+    tu.cursor().children().move_iter().find(|cursor| cursor.kind() == kind && cursor.usr().as_slice() == usr)
+}
+
+// Class or protocol referenced at point or the one containing point
+pub fn type_at(tu: &TranslationUnit, file_path: &Path, line: uint, column: uint) -> Option<Cursor> {
+    let range = tu.range_at(file_path, (line, column), (line, column));
+    let cursors = tu.enclosing_cursors(&range);
+
+    let declaration = match cursors.last() {
+        Some(cursor) if cursor.kind() == CXCursor_ObjCClassRef || cursor.kind() == CXCursor_ObjCSuperClassRef ||
+                        cursor.kind() == CXCursor_ObjCProtocolRef => cursor.referenced(),
+        _ => match cursors.iter().rev().find(|cursor| is_type_declaration(cursor.kind())) {
+            Some(cursor) => *cursor,
+            None => return None
+        }
+    };
+
+    // Implementations and categories are resolved to the class they belong to
+    let (usr, kind) = match declaration.kind() {
+        CXCursor_ObjCProtocolDecl => (declaration.usr(), CXCursor_ObjCProtocolDecl),
+        CXCursor_ObjCCategoryDecl | CXCursor_ObjCCategoryImplDecl => match extended_class(&declaration) {
+            Some(class) => (class.usr(), CXCursor_ObjCInterfaceDecl),
+            None => return None
+        },
+        _ => (declaration.usr(), CXCursor_ObjCInterfaceDecl)
+    };
+    find_definition(tu, usr.as_slice(), kind)
+}
+
+fn collect_supertypes(tu: &TranslationUnit, declaration: &Cursor, depth: uint,
+                      seen: &mut Vec<~str>, entries: &mut Vec<TypeEntry>) {
+    for child in declaration.children().iter() {
+        let (relation, kind) = match child.kind() {
+            CXCursor_ObjCSuperClassRef => ("superclass", CXCursor_ObjCInterfaceDecl),
+            CXCursor_ObjCProtocolRef => ("protocol", CXCursor_ObjCProtocolDecl),
+            _ => continue
+        };
+
+        let usr = child.referenced().usr();
+        if seen.contains(&usr) { continue; }
+        seen.push(usr.clone());
+
+        match find_definition(tu, usr.as_slice(), kind) {
+            Some(supertype) => {
+                entries.push(TypeEntry::new(relation, depth, &supertype));
+                collect_supertypes(tu, &supertype, depth + 1, seen, entries);
+            },
+            None => entries.push(TypeEntry::new(relation, depth, &child.referenced()))
+        }
+    }
+
+    if declaration.kind() != CXCursor_ObjCInterfaceDecl { return; }
+
+    let class_usr = declaration.usr();
+    for category in tu.cursor().children().iter().filter(|c| c.kind() == CXCursor_ObjCCategoryDecl) {
+        match extended_class(category) {
+            Some(class) if class.usr() == class_usr => {
+                entries.push(TypeEntry::new("category", depth, category));
+                collect_supertypes(tu, category, depth + 1, seen, entries);
+            },
+            _ => ()
+        }
+    }
+}
+
+// Superclass chain, adopted protocols and categories, depth starts from 1
+pub fn supertypes(tu: &TranslationUnit, declaration: &Cursor) -> Vec<TypeEntry> {
+    let mut entries = Vec::new();
+    let mut seen = vec!(declaration.usr());
+    collect_supertypes(tu, declaration, 1, &mut seen, &mut entries);
+    entries
+}
+
+/**
+ * Subtype Relations
+ **/
+
+// Direct relations between types collected from translation units
+pub struct TypeRelations {
+    relations: Vec<(~str, TypeEntry)>
+}
+
+impl TypeRelations {
+    pub fn new() -> TypeRelations {
+        TypeRelations { relations: Vec::new() }
+    }
+
+    fn add(&mut self, parent_usr: ~str, entry: TypeEntry) {
+        let exists = self.relations.iter().any(|&(ref parent, ref existing)| {
+            *parent == parent_usr && existing.usr == entry.usr
+        });
+        if !exists { self.relations.push((parent_usr, entry)); }
+    }
+
+    // Headers are included into many translation units, relations are collected once
+    pub fn collect(&mut self, tu: &TranslationUnit) {
+        for declaration in tu.cursor().children().iter() {
+            let subtype = match declaration.kind() {
+                CXCursor_ObjCInterfaceDecl | CXCursor_ObjCProtocolDecl => *declaration,
+                CXCursor_ObjCCategoryDecl => match extended_class(declaration) {
+                    Some(class) => class,
+                    None => continue
+                },
+                _ => continue
+            };
+
+            for child in declaration.children().iter() {
+                let relation = match child.kind() {
+                    CXCursor_ObjCSuperClassRef => "subclass",
+                    CXCursor_ObjCProtocolRef => "conformer",
+                    _ => continue
+                };
+                let mut entry = TypeEntry::new(relation, 1, &subtype);
+                if declaration.kind() == CXCursor_ObjCCategoryDecl {
+                    entry.name = display_name(declaration);
+                    entry.location = declaration.location();
+                }
+                self.add(child.referenced().usr(), entry);
+            }
+        }
+    }
+
+    // All known subclasses and conformers of the type, depth starts from 1
+    pub fn subtypes(&self, usr: &str) -> Vec<TypeEntry> {
+        let mut entries = Vec::new();
+        let mut seen = vec!(usr.to_owned());
+        let mut queue = vec!((usr.to_owned(), 1u));
+
+        while queue.len() > 0 {
+            let (parent_usr, depth) = queue.shift().unwrap();
+            for &(ref parent, ref entry) in self.relations.iter() {
+                if *parent != parent_usr || seen.contains(&entry.usr) { continue; }
+                seen.push(entry.usr.clone());
+                queue.push((entry.usr.clone(), depth + 1));
+                entries.push(TypeEntry { relation: entry.relation, depth: depth, name: entry.name.clone(),
+                                         usr: entry.usr.clone(), location: entry.location.clone() });
+            }
+        }
+        entries
+    }
+}
@@ -9,9 +9,11 @@ extern crate regex;
 #[phase(syntax)] extern crate regex_macros;
 
 extern crate collections;
+extern crate libc;
 extern crate serialize;
 #[phase(syntax, link)] extern crate log;
 extern crate getopts;
+extern crate time;
 
 extern crate rclang;
 extern crate rfsevents;
@@ -19,17 +21,46 @@ extern crate rfsevents;
 use getopts::*;
 use std::os;
 
+use rclang::code_completion::SnippetFormat;
+use rclang::source_file::{ColumnEncoding, ByteColumn};
+
+mod ast_cache;
+mod completion_cache;
+mod call_hierarchy;
+mod config;
+mod fuzzy;
 mod helpers;
+mod navigation;
+mod overrides;
+mod signature_help;
+mod type_hierarchy;
 mod xcodebuild;
 mod xcodebuild_parser;
 
 fn opts() -> ~[OptGroup] {
-    ~[optopt("l", "location", "location(line:column) for completion", "LOCATION"),
+    ~[optopt("l", "location", "location(line:column) for completion or range(line:column-line:column) for select-expansion", "LOCATION"),
       optopt("p", "prefix", "prefix for filtering completion results", "PREFIX"),
+      optopt("n", "limit", "maximum number of completion results", "LIMIT"),
+      optopt("f", "snippet-format", "completion snippet format(yas or lsp, yas snippets also work with UltiSnips)", "FORMAT"),
+      optmulti("", "completion-option", "include macros, patterns, comments, fixits or skip-preamble into completion, can be repeated", "OPTION"),
+      optmulti("", "parse-option", "include detailed-preprocessing-record, skip-function-bodies, keep-going or other flags into parsing for stats, can be repeated", "OPTION"),
+      optopt("", "column-encoding", "how columns in locations are counted: byte(default), char or utf-16, location can be a byte offset too", "ENCODING"),
+      optflag("", "no-cache", "Request fresh completion results instead of re-filtering cached ones"),
+      optflag("", "spelling-location", "Return where symbols are spelled(ex: inside macro definition) instead of macro usage for goto commands"),
       optopt("o", "original", "path to the original file, used with commands on temp buffers", "PATH"),
-      optopt("w", "workspace", "Workspace name(without extension), used with compilation-database", "WORKSPACE"),
+      optopt("w", "workspace", "Workspace name(without extension, detected if missing), used with compilation-database", "WORKSPACE"),
+      optopt("", "project", "Project name(without extension), used with compilation-database instead of workspace", "PROJECT"),
       optopt("s", "scheme", "Scheme name(defaults to workspace), used with compilation-database", "SCHEME"),
-      optopt("t", "sdk-target", "SDK(iphonesimulator7.0) to use with compilation-database", "TARGET"),
+      optopt("t", "sdk-target", "SDK(defaults to iphonesimulator) to use with compilation-database", "TARGET"),
+      optopt("", "configuration", "Build configuration to use with compilation-database", "CONFIGURATION"),
+      optopt("", "destination", "Build destination(xcodebuild format) to use with compilation-database", "DESTINATION"),
+      optopt("", "derived-data-path", "Derived data path to use with compilation-database", "PATH"),
+      optopt("", "xcconfig", "Build settings file to use with compilation-database", "PATH"),
+      optmulti("", "build-setting", "Build setting(KEY=VALUE) to use with compilation-database, can be repeated", "SETTING"),
+      optopt("a", "arch", "Arch to keep for files built for several archs(first, all or arch name), used with compilation-database", "ARCH"),
+      optmulti("g", "target", "Target to include into compilation-database, can be repeated", "TARGET"),
+      optflag("i", "incremental", "Merge results of non-clean build into existing compilation database"),
+      optflag("d", "dry-run", "Generate compilation database from xcodebuild -dry-run without compiling"),
       optflag("c", "continuous", "Automatically refresh compilation database when new files added")]
 }
 
@@ -39,7 +70,21 @@ Available commands:
     help:                 print this help menu
     syntax-check:         perform syntax check on the file
     code-completion:      return completion options for the location(line:column)
-    goto-definition:      return definition location for the specific location(line:column)
+    completion-context:   return completion context and container for the location(line:column)
+    signature-help:       return signatures of the call at the location(line:column) with active parameter
+    highlight:            return semantic class of identifiers(class, protocol, selector, ivar, property, etc.) with ranges
+    select-expansion:     return enclosing expressions, statements, methods and classes for the range(line:column-line:column)
+    supertypes:           return superclasses, protocols and categories of the class or protocol at the location(line:column)
+    subtypes:             return subclasses and conformers of the class or protocol at the location(line:column)
+    incoming-calls:       return callers of the function or method at the location(line:column) with call sites
+    outgoing-calls:       return callees of the function or method at the location(line:column) with call sites
+    goto-super-method:    return methods(including protocol declarations) overridden by the method at the location(line:column)
+    find-overrides:       return implementations overriding the method at the location(line:column)
+    describe:             return kind, type and Objective-C attributes of the declaration at the location(line:column)
+    stats:                return parse time and memory usage of the translation unit
+    goto-declaration:     return declaration locations for the specific location(line:column)
+    goto-definition:      return definition locations for the specific location(line:column)
+    goto-implementation:  return implementation locations for the specific location(line:column)
     compilation-database: performs project compilation and processes result into compilation database"##;
 
     let brief = format!("Usage: {} [command] [options] file_path\n{}", program, commands_help);
@@ -79,25 +124,187 @@ pub fn main() {
         return;
     }
 
+    let encoding = match opt_matches.opt_str("column-encoding") {
+        Some(e) => match ColumnEncoding::from_str(e) {
+            Some(encoding) => encoding,
+            None => fail!("Unknown column encoding: {}", e)
+        },
+        None => ByteColumn
+    };
+    let location = opt_matches.opt_str("l").map(|l| {
+        match helpers::normalize_location(&input, l, encoding) {
+            Ok(location) => location,
+            Err(e) => fail!("{}", e)
+        }
+    });
+
     if command == ~"code-completion" {
-        let loc = match opt_matches.opt_str("l") {
+        let loc = match location.clone() {
             Some(l) => l, None => fail!("Missing completion location")
         };
 
-        let prefix = match opt_matches.opt_str("p") { Some(p) => p, None => ~"" };
-        return match helpers::code_completion(&original, &input, loc, prefix) {
+        let mut options = helpers::CompletionOptions::new(opt_matches.opt_str("p").unwrap_or(~""));
+        options.cache = !opt_matches.opt_present("no-cache");
+        options.limit = match opt_matches.opt_str("n") {
+            Some(n) => match from_str::<uint>(n) {
+                Some(limit) => Some(limit),
+                None => fail!("Invalid completion limit: {}", n)
+            },
+            None => None
+        };
+        options.format = match opt_matches.opt_str("f") {
+            Some(f) => match SnippetFormat::from_str(f) {
+                Some(format) => Some(format),
+                None => fail!("Unknown snippet format: {}", f)
+            },
+            None => None
+        };
+        for name in opt_matches.opt_strs("completion-option").iter() {
+            match config::parse_completion_flag(name.as_slice()) {
+                Ok(flag) => options.flags.push(flag),
+                Err(e) => fail!("{}", e)
+            }
+        }
+
+        return match helpers::code_completion(&original, &input, loc, &options) {
             Ok(completion) => print!("{}", completion),
             Err(e) => fail!("{}", e)
         };
     }
 
-    if command == ~"goto-definition" {
-        let loc = match opt_matches.opt_str("l") {
+    if command == ~"completion-context" {
+        let loc = match location.clone() {
             Some(l) => l, None => fail!("Missing completion location")
         };
 
-        return match helpers::goto_definition(&original, &input, loc) {
-            Ok(location) => print!("{}", location),
+        return match helpers::completion_context(&original, &input, loc) {
+            Ok(context) => print!("{}", context),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"signature-help" {
+        let loc = match location.clone() {
+            Some(l) => l, None => fail!("Missing completion location")
+        };
+
+        return match helpers::signature_help(&original, &input, loc) {
+            Ok(signatures) => print!("{}", signatures),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"highlight" {
+        return match helpers::highlight(&original, &input, encoding) {
+            Ok(classes) => print!("{}", classes),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"select-expansion" {
+        let range = match location.clone() {
+            Some(l) => l, None => fail!("Missing selection range")
+        };
+
+        return match helpers::select_expansion(&original, &input, range, encoding) {
+            Ok(expansion) => print!("{}", expansion),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"supertypes" || command == ~"subtypes" {
+        let loc = match location.clone() {
+            Some(l) => l, None => fail!("Missing type location")
+        };
+
+        let result = if command == ~"supertypes" {
+            helpers::supertypes(&original, &input, loc, encoding)
+        } else {
+            helpers::subtypes(&original, &input, loc, encoding)
+        };
+        return match result {
+            Ok(types) => print!("{}", types),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"incoming-calls" || command == ~"outgoing-calls" {
+        let loc = match location.clone() {
+            Some(l) => l, None => fail!("Missing function location")
+        };
+
+        let result = if command == ~"incoming-calls" {
+            helpers::incoming_calls(&original, &input, loc, encoding)
+        } else {
+            helpers::outgoing_calls(&original, &input, loc, encoding)
+        };
+        return match result {
+            Ok(calls) => print!("{}", calls),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"goto-super-method" || command == ~"find-overrides" {
+        let loc = match location.clone() {
+            Some(l) => l, None => fail!("Missing method location")
+        };
+
+        let result = if command == ~"goto-super-method" {
+            helpers::goto_super_method(&original, &input, loc, encoding)
+        } else {
+            helpers::find_overrides(&original, &input, loc, encoding)
+        };
+        return match result {
+            Ok(methods) => print!("{}", methods),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"describe" {
+        let loc = match location.clone() {
+            Some(l) => l, None => fail!("Missing declaration location")
+        };
+
+        return match helpers::describe(&original, &input, loc, encoding) {
+            Ok(description) => print!("{}", description),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"stats" {
+        let mut flags = Vec::new();
+        for name in opt_matches.opt_strs("parse-option").iter() {
+            match config::parse_translation_unit_flag(name.as_slice()) {
+                Ok(flag) => flags.push(flag),
+                Err(e) => fail!("{}", e)
+            }
+        }
+
+        return match helpers::stats(&original, &input, flags.as_slice()) {
+            Ok(stats) => print!("{}", stats),
+            Err(e) => fail!("{}", e)
+        };
+    }
+
+    if command == ~"goto-declaration" || command == ~"goto-definition" || command == ~"goto-implementation" {
+        let loc = match location.clone() {
+            Some(l) => l, None => fail!("Missing symbol location")
+        };
+
+        let spelling = opt_matches.opt_present("spelling-location");
+        let result = if command == ~"goto-declaration" {
+            helpers::goto_declaration(&original, &input, loc, spelling)
+        } else if command == ~"goto-definition" {
+            helpers::goto_definition(&original, &input, loc, spelling)
+        } else {
+            helpers::goto_implementation(&original, &input, loc, spelling)
+        };
+        return match result {
+            Ok(locations) => {
+                for location in locations.iter() {
+                    println!("{}", helpers::encode_location(location.as_slice(), encoding));
+                }
+            },
             Err(e) => fail!("{}", e)
         };
     }
@@ -110,14 +317,42 @@ pub fn main() {
     }
 
     if command == ~"compilation-database" {
-        let workspace = match opt_matches.opt_str("w") {
-            Some(w) => w,
-            None => fail!("Workspace can't be empty")
+        let config = match config::Config::find(&input) {
+            Ok(Some(c)) => c,
+            Ok(None) => config::Config::new(os::getcwd()),
+            Err(e) => fail!("{}", e)
+        };
+        let or_config = |value: Option<~str>, default: &Option<~str>| value.or(default.clone());
+
+        let workspace = or_config(opt_matches.opt_str("w"), &config.workspace);
+        let project = or_config(opt_matches.opt_str("project"), &config.project);
+        let container = match (workspace, project) {
+            (Some(w), _) => xcodebuild::BuildContainer::workspace(w.as_slice()),
+            (None, Some(p)) => xcodebuild::BuildContainer::project(p.as_slice()),
+            (None, None) => match xcodebuild::BuildContainer::detect(&os::getcwd()) {
+                Some(c) => c,
+                None => fail!("Unable to find workspace or project in current directory")
+            }
+        };
+
+        let mut options = xcodebuild::BuildOptions::new(container);
+        options.scheme = or_config(opt_matches.opt_str("s"), &config.scheme);
+        options.sdk = Some(or_config(opt_matches.opt_str("t"), &config.sdk).unwrap_or(~"iphonesimulator"));
+        options.configuration = or_config(opt_matches.opt_str("configuration"), &config.configuration);
+        options.destination = opt_matches.opt_str("destination");
+        options.derived_data = opt_matches.opt_str("derived-data-path");
+        options.xcconfig = opt_matches.opt_str("xcconfig");
+        options.build_settings = opt_matches.opt_strs("build-setting").move_iter().collect();
+        match opt_matches.opt_str("a") {
+            Some(a) => options.arch = xcodebuild_parser::ArchPolicy::from_str(a.as_slice()),
+            None => ()
         };
-        let scheme = match opt_matches.opt_str("s") { Some(s) => s, None => workspace.clone() };
-        let sdk = match opt_matches.opt_str("t") { Some(t) => t, None => ~"iphonesimulator7.1" };
+        options.targets = opt_matches.opt_strs("g").move_iter().collect();
+        options.incremental = opt_matches.opt_present("i");
+        options.dry_run = opt_matches.opt_present("d");
+        options.excluded = config.exclude.iter().map(|e| config.directory.join(e.as_slice())).collect();
 
-        let watcher = xcodebuild::XCodeBuildWatcher::new(os::getcwd(), workspace, scheme, sdk);
+        let watcher = xcodebuild::XCodeBuildWatcher::new(os::getcwd(), options);
         let result = if opt_matches.opt_present("c") { watcher.watch() } else { watcher.run() };
         return match result { Ok(_) => (), Err(e) => fail!("{}", e) }
     }
@@ -1,26 +1,138 @@
-use std::io::{fs, Process, Writer};
+use std::io::{fs, Process, Reader, Writer};
 use std::str;
 
 use serialize::{json, Encodable};
 
-use xcodebuild_parser::{XCodeBuildParser, CommandData};
+use xcodebuild_parser::{XCodeBuildParser, CommandData, CommandMetadata, ArchPolicy, FirstSeenArch};
+use xcodebuild_parser::{dedup_commands, filter_targets, merge_commands, remove_missing_files};
 use rfsevents::*;
 
+/**
+ * Build Options
+ **/
+
+pub enum BuildContainer {
+    Workspace(~str),
+    Project(~str)
+}
+
+impl BuildContainer {
+    pub fn workspace(name: &str) -> BuildContainer {
+        if name.ends_with(".xcworkspace") { Workspace(name.to_owned()) } else { Workspace(name + ".xcworkspace") }
+    }
+
+    pub fn project(name: &str) -> BuildContainer {
+        if name.ends_with(".xcodeproj") { Project(name.to_owned()) } else { Project(name + ".xcodeproj") }
+    }
+
+    // Workspace is preferred, since with CocoaPods project alone doesn't build
+    pub fn detect(folder: &Path) -> Option<BuildContainer> {
+        let files = match fs::readdir(folder) { Ok(f) => f, Err(_) => return None };
+        let with_extension = |extension: &str| {
+            files.iter().find(|f| f.extension_str() == Some(extension))
+                 .and_then(|f| f.filename_str()).map(|name| name.to_owned())
+        };
+
+        match with_extension("xcworkspace") {
+            Some(workspace) => Some(Workspace(workspace)),
+            None => with_extension("xcodeproj").map(|project| Project(project))
+        }
+    }
+
+    pub fn name(&self) -> ~str {
+        let file_name = match *self { Workspace(ref name) => name, Project(ref name) => name };
+        Path::new(file_name.as_slice()).filestem_str().unwrap_or("").to_owned()
+    }
+
+    fn args(&self) -> Vec<~str> {
+        match *self {
+            Workspace(ref name) => vec!(~"-workspace", name.clone()),
+            Project(ref name) => vec!(~"-project", name.clone())
+        }
+    }
+}
+
+pub struct BuildOptions {
+    pub container:      BuildContainer,
+    pub scheme:         Option<~str>,
+    pub sdk:            Option<~str>,
+    pub configuration:  Option<~str>,
+    pub destination:    Option<~str>,
+    pub derived_data:   Option<~str>,
+    pub xcconfig:       Option<~str>,
+    pub build_settings: Vec<~str>,
+    pub arch:           ArchPolicy,
+    pub targets:        Vec<~str>,
+    pub excluded:       Vec<Path>,
+    pub incremental:    bool,
+    pub dry_run:        bool
+}
+
+impl BuildOptions {
+    pub fn new(container: BuildContainer) -> BuildOptions {
+        BuildOptions {
+            container:      container,
+            scheme:         None,
+            sdk:            None,
+            configuration:  None,
+            destination:    None,
+            derived_data:   None,
+            xcconfig:       None,
+            build_settings: Vec::new(),
+            arch:           FirstSeenArch,
+            targets:        Vec::new(),
+            excluded:       Vec::new(),
+            incremental:    false,
+            dry_run:        false
+        }
+    }
+
+    pub fn xcodebuild_args(&self) -> Vec<~str> {
+        let mut args = self.container.args();
+
+        // Workspaces can only be built with a scheme, projects fall back to the first target
+        let scheme = match (&self.container, &self.scheme) {
+            (_, &Some(ref scheme)) => Some(scheme.clone()),
+            (&Workspace(_), &None) => Some(self.container.name()),
+            (&Project(_), &None) => None
+        };
+        let optional_args = [("-scheme", scheme), ("-sdk", self.sdk.clone()),
+                             ("-configuration", self.configuration.clone()),
+                             ("-destination", self.destination.clone()),
+                             ("-xcconfig", self.xcconfig.clone())];
+        for &(flag, ref value) in optional_args.iter() {
+            match *value {
+                Some(ref v) => args.push_all([flag.to_owned(), v.clone()]),
+                None => ()
+            }
+        }
+
+        match self.derived_data {
+            Some(ref path) => args.push_all([~"-derivedDataPath", path.clone()]),
+            None => ()
+        }
+        // Dry run of clean build lists every compile step while using the regular
+        // derived data, so header maps and generated headers of previous builds resolve
+        if self.dry_run { args.push(~"-dry-run"); }
+        args.push_all(self.build_settings.as_slice());
+        if !self.incremental { args.push(~"clean"); }
+        args.push(~"build");
+        args
+    }
+}
+
+/**
+ * Watcher
+ **/
+
 pub struct XCodeBuildWatcher {
-    folder:    Path,
-    workspace: ~str,
-    scheme:    ~str,
-    sdk:       ~str
+    folder:  Path,
+    options: BuildOptions
 }
 
 impl XCodeBuildWatcher {
-    pub fn new(folder: Path, workspace: ~str, scheme: ~str, sdk: ~str) -> XCodeBuildWatcher {
-        XCodeBuildWatcher {
-            folder:    folder,
-            workspace: workspace,
-            scheme:    scheme,
-            sdk:       sdk
-        }
+    pub fn new(folder: Path, options: BuildOptions) -> XCodeBuildWatcher {
+        XCodeBuildWatcher { folder: folder, options: options }
     }
 
     pub fn watch(&self) -> Result<(), ~str> {
@@ -28,8 +140,10 @@ impl XCodeBuildWatcher {
 
         println!("Listening for changes in folder: {}", self.folder.as_str().unwrap());
         let callback = |path: Path, flags: FSEventStreamEventFlags, _id: FSEventStreamEventId| {
-            if (flags as uint & kFSEventStreamEventFlagItemCreated as uint) == 0 { return }
-            println!("Found new file: {}", path.as_str().unwrap());
+            let mut trigger_flags = kFSEventStreamEventFlagItemCreated as uint;
+            if self.options.incremental { trigger_flags |= kFSEventStreamEventFlagItemRemoved as uint; }
+            if (flags as uint & trigger_flags) == 0 { return }
+            println!("Found new or removed file: {}", path.as_str().unwrap());
             match self.run() { Ok(_) => (), Err(e) => println!("{}\x07", e) };
         };
         let stream = FSEventStream::new(&self.folder, 3.0, callback);
@@ -39,26 +153,38 @@ impl XCodeBuildWatcher {
     }
 
     pub fn run(&self) -> Result<(), ~str> {
-        info!("Building: {}.xcworkspace", self.workspace);
+        info!("Building: {}", self.options.container.name());
         let output_str = try!(self.build());
         debug!("{}", output_str);
 
+        let existing = if self.options.incremental { self.read_c_db_json() } else { Vec::new() };
         let mut parser = XCodeBuildParser::new();
-        let compile_commands = parser.parse_output(output_str);
+        parser.add_existing_commands(existing.as_slice());
+        let compile_commands = dedup_commands(parser.parse_output(output_str), &self.options.arch);
+        let compile_commands = filter_targets(compile_commands, self.options.targets.as_slice());
+        let compile_commands: Vec<CommandData> = compile_commands.move_iter().filter(|c| {
+            let file_path = Path::new(c.directory.as_slice()).join(c.file.as_slice());
+            !self.options.excluded.iter().any(|excluded| excluded.is_ancestor_of(&file_path))
+        }).collect();
+        let compile_commands = if self.options.incremental {
+            remove_missing_files(merge_commands(existing, compile_commands))
+        } else {
+            compile_commands
+        };
 
         info!("Writing compilation_db");
+        let metadata = compile_commands.iter().map(|c| c.metadata()).collect();
         try!(self.write_c_db_json(compile_commands));
+        try!(self.write_metadata_json(metadata));
 
         return Ok(());
     }
 
     fn build(&self) -> Result<~str, ~str> {
-        let args = [~"-workspace", (self.workspace + ".xcworkspace"),
-                    ~"-scheme", self.scheme.to_owned(),
-                    ~"-sdk", self.sdk.to_owned(),
-                    ~"clean", ~"build"];
+        let args = self.options.xcodebuild_args();
+        debug!("xcodebuild {}", args.connect(" "));
 
-        let process_output = match Process::output("xcodebuild", args) {
+        let process_output = match Process::output("xcodebuild", args.as_slice()) {
             Ok(output) => output,
             Err(e) => return Err(format!("Failed to execute process: {}", e)),
         };
@@ -74,6 +200,44 @@ impl XCodeBuildWatcher {
         }
     }
 
+    fn read_json(&self, file_name: &str) -> Vec<json::Json> {
+        let mut file = match fs::File::open(&self.folder.join(file_name)) {
+            Ok(f) => f,
+            Err(_) => return Vec::new()
+        };
+
+        match json::from_reader(&mut file as &mut Reader) {
+            Ok(json::List(entries)) => entries,
+            _ => Vec::new()
+        }
+    }
+
+    fn read_c_db_json(&self) -> Vec<CommandData> {
+        let metadata = self.read_json("compile_commands_metadata.json");
+        let field = |entry: &json::Json, name: &str| -> ~str {
+            match entry.find(&name.to_owned()).and_then(|value| value.as_string()) {
+                Some(value) => value.to_owned(),
+                None => ~""
+            }
+        };
+
+        self.read_json("compile_commands.json").iter().map(|entry| {
+            let file = field(entry, "file");
+            let meta = metadata.iter().find(|m| field(*m, "file") == file);
+            let meta_field = |name: &str| match meta { Some(m) => field(m, name), None => ~"" };
+
+            CommandData {
+                directory:     field(entry, "directory"),
+                command:       field(entry, "command"),
+                file:          file.clone(),
+                arch:          meta_field("arch"),
+                target:        meta_field("target"),
+                project:       meta_field("project"),
+                configuration: meta_field("configuration")
+            }
+        }).collect()
+    }
+
     fn write_c_db_json(&self, compile_commands: Vec<CommandData>) -> Result<(), ~str> {
         let encoding_result = match fs::File::create(&self.folder.join("compile_commands.json")) {
             Ok(f) => {
@@ -89,4 +253,80 @@ impl XCodeBuildWatcher {
             Err(e) => return Err(format!("Unable to encode database into json: {}", e))
         };
     }
+
+    fn write_metadata_json(&self, metadata: Vec<CommandMetadata>) -> Result<(), ~str> {
+        let encoding_result = match fs::File::create(&self.folder.join("compile_commands_metadata.json")) {
+            Ok(f) => {
+                let mut f2 = f;
+                let encoder = &mut json::PrettyEncoder::new(&mut f2 as &mut Writer);
+                metadata.encode(encoder)
+            },
+            Err(e) => return Err(format!("Unable to create compile_commands_metadata.json: {}", e))
+        };
+
+        match encoding_result {
+            Ok(_) => return Ok(()),
+            Err(e) => return Err(format!("Unable to encode metadata into json: {}", e))
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use xcodebuild::{BuildOptions, BuildContainer};
+
+    #[test]
+    fn workspace_args() {
+        let options = BuildOptions::new(BuildContainer::workspace("TestApplication"));
+        let args = options.xcodebuild_args();
+        assert!(args == vec!(~"-workspace", ~"TestApplication.xcworkspace",
+                             ~"-scheme", ~"TestApplication", ~"clean", ~"build"));
+    }
+
+    #[test]
+    fn project_args() {
+        let mut options = BuildOptions::new(BuildContainer::project("TestApplication.xcodeproj"));
+        options.configuration = Some(~"Release");
+        options.destination = Some(~"platform=iOS Simulator,name=iPhone");
+        options.derived_data = Some(~"build");
+        options.build_settings = vec!(~"ONLY_ACTIVE_ARCH=YES");
+        options.incremental = true;
+
+        let args = options.xcodebuild_args();
+        assert!(args == vec!(~"-project", ~"TestApplication.xcodeproj",
+                             ~"-configuration", ~"Release",
+                             ~"-destination", ~"platform=iOS Simulator,name=iPhone",
+                             ~"-derivedDataPath", ~"build",
+                             ~"ONLY_ACTIVE_ARCH=YES", ~"build"));
+    }
+
+    #[test]
+    fn dry_run_args() {
+        let mut options = BuildOptions::new(BuildContainer::workspace("TestApplication"));
+        options.dry_run = true;
+
+        let args = options.xcodebuild_args();
+        assert!(args == vec!(~"-workspace", ~"TestApplication.xcworkspace",
+                             ~"-scheme", ~"TestApplication", ~"-dry-run", ~"clean", ~"build"));
+    }
+
+    #[test]
+    fn dry_run_derived_data_args() {
+        let mut options = BuildOptions::new(BuildContainer::project("TestApplication"));
+        options.derived_data = Some(~"build");
+        options.dry_run = true;
+        options.incremental = true;
+
+        let args = options.xcodebuild_args();
+        assert!(args == vec!(~"-project", ~"TestApplication.xcodeproj",
+                             ~"-derivedDataPath", ~"build", ~"-dry-run", ~"build"));
+    }
+
+    #[test]
+    fn detect_container() {
+        let container = BuildContainer::detect(&Path::new("./tests/TestApplication"));
+        assert!(container.is_some());
+        assert!(container.unwrap().name() == ~"TestApplication");
+    }
 }
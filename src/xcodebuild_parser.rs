@@ -1,15 +1,147 @@
-use collections::HashMap;
-use serialize::Encodable;
+use collections::{HashMap, HashSet};
+use serialize::{Encodable, Encoder};
 
-#[deriving(Encodable)]
 pub struct CommandData {
-    pub directory: ~str,
-    pub command:   ~str,
-    pub file:      ~str
+    pub directory:     ~str,
+    pub command:       ~str,
+    pub file:          ~str,
+    pub arch:          ~str,
+    pub target:        ~str,
+    pub project:       ~str,
+    pub configuration: ~str
+}
+
+// Only fields known to the JSON compilation database format are written,
+// libclang refuses to load entries with unknown keys.
+impl<E, S: Encoder<E>> Encodable<S, E> for CommandData {
+    fn encode(&self, s: &mut S) -> Result<(), E> {
+        s.emit_struct("CommandData", 3, |s| {
+            try!(s.emit_struct_field("directory", 0, |s| self.directory.encode(s)));
+            try!(s.emit_struct_field("command", 1, |s| self.command.encode(s)));
+            s.emit_struct_field("file", 2, |s| self.file.encode(s))
+        })
+    }
+}
+
+impl CommandData {
+    pub fn metadata(&self) -> CommandMetadata {
+        CommandMetadata {
+            file:          self.file.clone(),
+            arch:          self.arch.clone(),
+            target:        self.target.clone(),
+            project:       self.project.clone(),
+            configuration: self.configuration.clone()
+        }
+    }
+}
+
+/**
+ * Build metadata for the compilation database entry, stored separately from it
+ **/
+
+#[deriving(Encodable)]
+pub struct CommandMetadata {
+    pub file:          ~str,
+    pub arch:          ~str,
+    pub target:        ~str,
+    pub project:       ~str,
+    pub configuration: ~str
+}
+
+pub fn filter_targets(commands: Vec<CommandData>, targets: &[~str]) -> Vec<CommandData> {
+    if targets.len() == 0 { return commands; }
+    commands.move_iter().filter(|c| targets.contains(&c.target)).collect()
+}
+
+// Updated files replace all their existing entries, so archs built
+// previously for these files don't survive the merge
+pub fn merge_commands(existing: Vec<CommandData>, updated: Vec<CommandData>) -> Vec<CommandData> {
+    let updated_files: HashSet<~str> = updated.iter().map(|c| c.file.clone()).collect();
+    let mut result: Vec<CommandData> = existing.move_iter().filter(|c| !updated_files.contains(&c.file)).collect();
+    result.push_all_move(updated);
+    result
+}
+
+pub fn remove_missing_files(commands: Vec<CommandData>) -> Vec<CommandData> {
+    commands.move_iter().filter(|c| {
+        Path::new(c.directory.as_slice()).join(c.file.as_slice()).exists()
+    }).collect()
+}
+
+/**
+ * Policy for files compiled for several architectures
+ **/
+
+pub enum ArchPolicy {
+    KeepAllArchs,
+    FirstSeenArch,
+    PreferredArch(~str)
+}
+
+impl ArchPolicy {
+    pub fn from_str(policy: &str) -> ArchPolicy {
+        match policy {
+            "all"   => KeepAllArchs,
+            "first" => FirstSeenArch,
+            arch    => PreferredArch(arch.to_owned())
+        }
+    }
+}
+
+pub fn dedup_commands(commands: Vec<CommandData>, policy: &ArchPolicy) -> Vec<CommandData> {
+    let preferred = match *policy {
+        KeepAllArchs => return commands,
+        FirstSeenArch => None,
+        PreferredArch(ref arch) => Some(arch.clone())
+    };
+
+    let mut result: Vec<CommandData> = Vec::new();
+    let mut positions: HashMap<~str, uint> = HashMap::new();
+    for command_data in commands.move_iter() {
+        let position = positions.find_copy(&command_data.file);
+        match position {
+            None => {
+                positions.insert(command_data.file.clone(), result.len());
+                result.push(command_data);
+            },
+            Some(idx) => {
+                let replace = match preferred {
+                    Some(ref arch) => command_data.arch == *arch && result.get(idx).arch != *arch,
+                    None => false
+                };
+                if replace { *result.get_mut(idx) = command_data; }
+            }
+        }
+    }
+
+    result
+}
+
+// Steps are followed by `cd` and a varying number of `export`/`setenv` lines
+// (dry runs and newer Xcode versions add deployment target exports), the
+// first other line is the tool invocation.
+fn next_invocation<'a, T: Iterator<&'a str>>(lines: &mut T) -> Option<(~str, &'a str)> {
+    let mut directory = ~"";
+    loop {
+        let line = match lines.next() { Some(l) => l, None => break };
+        let trimmed = line.trim();
+        if trimmed.starts_with("cd ") {
+            directory = trimmed.slice_from(3).to_owned();
+        } else if !trimmed.starts_with("export ") && !trimmed.starts_with("setenv ") {
+            return Some((directory, line));
+        }
+    }
+
+    None
 }
 
 pub struct XCodeBuildParser {
-    pch_map: HashMap<~str, ~str>
+    pch_map:       HashMap<~str, ~str>,
+    // Prefix headers of the files in the existing compilation database
+    existing_pch:  HashMap<~str, ~str>,
+    target:        ~str,
+    project:       ~str,
+    configuration: ~str
 }
 
 impl XCodeBuildParser {
@@ -22,22 +154,54 @@ impl XCodeBuildParser {
         self.pch_map.insert(captures.name("output").to_owned(), captures.name("input").to_owned());
     }
 
-    fn parse_command_line(&self, command_line: &str) -> (~str, ~str) {
+    fn process_section_header(&mut self, header_line: &str) {
+        let re = regex!(r"^=== BUILD (?:AGGREGATE )?TARGET (?P<target>.+) OF PROJECT (?P<project>.+) WITH CONFIGURATION (?P<configuration>.+) ===$");
+        let captures_opt = re.captures(header_line);
+        if captures_opt.is_none() { return; }
+
+        let captures = captures_opt.unwrap();
+        self.target = captures.name("target").to_owned();
+        self.project = captures.name("project").to_owned();
+        self.configuration = captures.name("configuration").to_owned();
+    }
+
+    fn parse_command_line(&self, command_line: &str) -> (~str, ~str, ~str) {
         let file_captures = regex!(r"\B-c (?P<file>.*?)\s").captures(command_line);
         if file_captures.is_none() { fail!("Unable to find file path in command: {}", command_line); }
         let file = file_captures.unwrap().name("file");
 
+        let arch = match regex!(r"-arch (?P<arch>\S+)").captures(command_line) {
+            Some(captures) => captures.name("arch").to_owned(),
+            None => ~""
+        };
+
         let pch_re = regex!(r"-include (?P<pch>.*pch)");
         let command = match pch_re.captures(command_line) {
             Some(captures) => {
+                // Incremental builds skip ProcessPCH for untouched headers, prefix
+                // header of the existing entry is kept for them
                 let compiled = captures.name("pch").to_owned();
-                let original = self.pch_header_for(&(compiled + ".pch"));
-                command_line.replace(compiled, original)
+                match self.pch_map.find(&(compiled + ".pch")).or(self.existing_pch.find(&file.to_owned())) {
+                    Some(original) => command_line.replace(compiled, original.as_slice()),
+                    None => command_line.to_owned()
+                }
             },
             None => command_line.to_owned()
         };
 
-        (file.to_owned(), command.trim().to_owned())
+        (file.to_owned(), command.trim().to_owned(), arch)
+    }
+
+    pub fn add_existing_commands(&mut self, commands: &[CommandData]) {
+        let pch_re = regex!(r"-include (?P<pch>.*pch)");
+        for command_data in commands.iter() {
+            match pch_re.captures(command_data.command.as_slice()) {
+                Some(captures) => {
+                    self.existing_pch.insert(command_data.file.clone(), captures.name("pch").to_owned());
+                },
+                None => ()
+            }
+        }
     }
 
     pub fn pch_header_for(&self, precompiled_header: &~str) -> ~str {
@@ -55,36 +219,57 @@ impl XCodeBuildParser {
         loop {
             let command_line = skip_iter.next();
             if command_line.is_none() { break; }
+
+            if command_line.unwrap().starts_with("=== ") {
+                self.process_section_header(command_line.unwrap());
+                continue;
+            }
             if skip_condition(&command_line.unwrap()) { continue; }
 
+            let (directory, invocation) = match next_invocation(&mut skip_iter) {
+                Some(step) => step,
+                None => break
+            };
+
             if command_line.unwrap().starts_with("ProcessPCH") {
-                skip_iter.next(); // cd
-                skip_iter.next(); // export LANG
-                skip_iter.next(); // export PATH
-                self.process_compiled_header(skip_iter.next().unwrap());
+                self.process_compiled_header(invocation);
                 continue;
             }
 
-            let directory = skip_iter.next().unwrap().replace("    cd ", "");
-            skip_iter.next(); // export LANG
-            skip_iter.next(); // export PATH
-            let (file, command) = self.parse_command_line(skip_iter.next().unwrap());
-            result.push(CommandData{ directory: directory, command: command, file: file });
+            let (file, command, arch) = self.parse_command_line(invocation);
+            result.push(CommandData {
+                directory:     directory,
+                command:       command,
+                file:          file,
+                arch:          arch,
+                target:        self.target.clone(),
+                project:       self.project.clone(),
+                configuration: self.configuration.clone()
+            });
         }
 
         result
     }
 
     pub fn new() -> XCodeBuildParser {
-        XCodeBuildParser { pch_map: HashMap::new() }
+        XCodeBuildParser {
+            pch_map:       HashMap::new(),
+            existing_pch:  HashMap::new(),
+            target:        ~"",
+            project:       ~"",
+            configuration: ~""
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
 
+    use std::os;
     use std::io::fs::File;
-    use xcodebuild_parser::XCodeBuildParser;
+    use xcodebuild_parser::{XCodeBuildParser, CommandData, dedup_commands, filter_targets, merge_commands};
+    use xcodebuild_parser::remove_missing_files;
+    use xcodebuild_parser::{KeepAllArchs, FirstSeenArch, PreferredArch};
 
     fn parser_with_pch() -> XCodeBuildParser {
         let output = r##"ProcessPCH /Users/arthurevstifeev/Library/Developer/Xcode/DerivedData/TestApplication-ggnvtdtbeunuqqgbdwrstauepclk/Build/Intermediates/PrecompiledHeaders/TestApplication-Prefix-gnsrlwixdykpdkeyczvocpwcwymh/TestApplication-Prefix.pch.pch TestApplication/TestApplication-Prefix.pch normal i386 objective-c com.apple.compilers.llvm.clang.1_0.compiler
@@ -121,6 +306,121 @@ mod test {
         assert!(command_data.command == ~"/Applications/Xcode.app/Contents/Developer/Toolchains/XcodeDefault.xctoolchain/usr/bin/clang -x objective-c -arch i386 -include /foo/TestApplication-Prefix.pch -c /foo/TestClass.m -o /baz/TestClass.o")
     }
 
+    fn command_data(file: &str, arch: &str) -> CommandData {
+        CommandData {
+            directory: ~"/foo",
+            command: format!("clang -arch {} -c {} -o /baz/out.o", arch, file),
+            file: file.to_owned(),
+            arch: arch.to_owned(),
+            target: ~"TestApplication",
+            project: ~"TestApplication",
+            configuration: ~"Debug"
+        }
+    }
+
+    #[test]
+    fn compile_c_arch() {
+        let output = r##"CompileC /baz/TestClass.o TestApplication/TestClass.m normal x86_64 objective-c com.apple.compilers.llvm.clang.1_0.compiler
+    cd /Users/arthurevstifeev/github/xclang_tool/tests/TestApplication
+    export LANG=en_US.US-ASCII
+    export PATH="/usr/bin:/bin"
+    /usr/bin/clang -x objective-c -arch x86_64 -c /foo/TestClass.m -o /baz/TestClass.o"##;
+
+        let mut parser = XCodeBuildParser::new();
+        let result = parser.parse_output(output);
+        assert!(result.get(0).arch == ~"x86_64");
+    }
+
+    #[test]
+    fn dedup_archs() {
+        let commands = || vec!(command_data("/foo/A.m", "armv7"), command_data("/foo/B.m", "armv7"),
+                               command_data("/foo/A.m", "i386"));
+
+        assert!(dedup_commands(commands(), &KeepAllArchs).len() == 3);
+
+        let first_seen = dedup_commands(commands(), &FirstSeenArch);
+        assert!(first_seen.len() == 2);
+        assert!(first_seen.get(0).arch == ~"armv7");
+
+        let preferred = dedup_commands(commands(), &PreferredArch(~"i386"));
+        assert!(preferred.len() == 2);
+        assert!(preferred.get(0).file == ~"/foo/A.m");
+        assert!(preferred.get(0).arch == ~"i386");
+        assert!(preferred.get(1).arch == ~"armv7");
+    }
+
+    #[test]
+    fn merge() {
+        let existing = vec!(command_data("/foo/A.m", "i386"), command_data("/foo/B.m", "i386"));
+        let updated = vec!(command_data("/foo/C.m", "i386"), command_data("/foo/A.m", "x86_64"));
+
+        let result = merge_commands(existing, updated);
+        assert!(result.len() == 3);
+        assert!(result.get(0).file == ~"/foo/B.m");
+        assert!(result.get(1).file == ~"/foo/C.m");
+        assert!(result.get(2).file == ~"/foo/A.m");
+        assert!(result.get(2).arch == ~"x86_64");
+    }
+
+    #[test]
+    fn merge_all_archs() {
+        let existing = vec!(command_data("/foo/A.m", "armv7"), command_data("/foo/A.m", "i386"),
+                            command_data("/foo/B.m", "i386"));
+        let updated = vec!(command_data("/foo/A.m", "arm64"), command_data("/foo/A.m", "x86_64"));
+
+        let result = merge_commands(existing, updated);
+        let archs: Vec<~str> = result.iter().filter(|c| c.file == ~"/foo/A.m").map(|c| c.arch.clone()).collect();
+        assert!(result.len() == 3);
+        assert!(archs == vec!(~"arm64", ~"x86_64"));
+    }
+
+    #[test]
+    fn remove_deleted_files() {
+        let mut existing = command_data("TestApplication/AppDelegate.m", "i386");
+        existing.directory = os::getcwd().join("tests/TestApplication").as_str().unwrap().to_owned();
+        let mut deleted = command_data("TestApplication/Deleted.m", "i386");
+        deleted.directory = existing.directory.clone();
+
+        let result = remove_missing_files(vec!(existing, deleted));
+        assert!(result.len() == 1);
+        assert!(result.get(0).file == ~"TestApplication/AppDelegate.m");
+    }
+
+    #[test]
+    fn compile_c_without_process_pch() {
+        let output = r##"CompileC /baz/TestClass.o TestApplication/TestClass.m normal i386 objective-c com.apple.compilers.llvm.clang.1_0.compiler
+    cd /Users/arthurevstifeev/github/xclang_tool/tests/TestApplication
+    export LANG=en_US.US-ASCII
+    /usr/bin/clang -x objective-c -arch i386 -include /bar/TestApplication-Prefix.pch -c /foo/TestClass.m -o /baz/TestClass.o"##;
+
+        let mut parser = XCodeBuildParser::new();
+        let result = parser.parse_output(output);
+        assert!(result.len() == 1);
+        assert!(result.get(0).command.contains("-include /bar/TestApplication-Prefix.pch "));
+
+        let mut existing = command_data("/foo/TestClass.m", "i386");
+        existing.command = ~"/usr/bin/clang -include /foo/TestApplication-Prefix.pch -c /foo/TestClass.m";
+        let mut parser = XCodeBuildParser::new();
+        parser.add_existing_commands([existing]);
+        let result = parser.parse_output(output);
+        assert!(result.get(0).command.contains("-include /foo/TestApplication-Prefix.pch "));
+    }
+
+    #[test]
+    fn parse_dry_run_output() {
+        let dry_run_log_path = Path::new("./tests/xcodebuild_dry_run_log");
+        let dry_run_log = File::open(&dry_run_log_path).read_to_str().unwrap();
+        let mut parser = XCodeBuildParser::new();
+        let results = parser.parse_output(dry_run_log);
+
+        assert!(results.len() == 3);
+        let command_data = results.get(2);
+        assert!(command_data.file == ~"/Users/arthurevstifeev/github/xclang_tool/tests/TestApplication/TestApplication/TestClass.m");
+        assert!(command_data.directory == ~"/Users/arthurevstifeev/github/xclang_tool/tests/TestApplication");
+        assert!(command_data.target == ~"TestApplication");
+        assert!(command_data.command.contains("-include /Users/arthurevstifeev/github/xclang_tool/tests/TestApplication/TestApplication/TestApplication-Prefix.pch "));
+    }
+
     #[test]
     fn parse_xcodebuild_output() {
         let xcodebuild_log_path = Path::new("./tests/xcodebuild_log");
@@ -130,4 +430,17 @@ mod test {
 
         assert!(results.len() == 75);
     }
+
+    #[test]
+    fn parse_section_headers() {
+        let xcodebuild_log_path = Path::new("./tests/xcodebuild_log");
+        let xcodebuild_log = File::open(&xcodebuild_log_path).read_to_str().unwrap();
+        let mut parser = XCodeBuildParser::new();
+        let results = parser.parse_output(xcodebuild_log);
+
+        assert!(results.iter().all(|c| c.configuration == ~"Debug"));
+        let app_commands = filter_targets(results, [~"Origami"]);
+        assert!(app_commands.len() == 43);
+        assert!(app_commands.iter().all(|c| c.project == ~"Origami"));
+    }
 }